@@ -0,0 +1,175 @@
+// src/annealing.rs
+use std::str::FromStr;
+
+use log::debug;
+use rand::Rng;
+
+use crate::decryption::validate_key;
+use crate::error::Result;
+use crate::fitness::Fitness;
+use crate::key_locks::KeyLocks;
+use crate::keygen::seeded_rng;
+
+// How the temperature falls from its initial to its final value over the iterations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureSchedule {
+    Linear,
+    #[default]
+    Geometric,
+}
+
+impl FromStr for TemperatureSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "linear" => Ok(TemperatureSchedule::Linear),
+            "geometric" => Ok(TemperatureSchedule::Geometric),
+            _ => Err(format!("unknown temperature schedule {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnealingConfig {
+    pub iterations: usize,
+    pub initial_temperature: f64,
+    pub final_temperature: f64,
+    pub schedule: TemperatureSchedule,
+    // Seeds the random number generator for reproducible runs, random when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for AnnealingConfig {
+    fn default() -> Self {
+        AnnealingConfig {
+            iterations: 20000,
+            initial_temperature: 10.0,
+            final_temperature: 0.05,
+            schedule: TemperatureSchedule::default(),
+            seed: None,
+        }
+    }
+}
+
+impl AnnealingConfig {
+    // Temperature at a fraction of the way through the run, from 0.0 to 1.0.
+    fn temperature(&self, progress: f64) -> f64 {
+        match self.schedule {
+            TemperatureSchedule::Linear => {
+                self.initial_temperature
+                    + (self.final_temperature - self.initial_temperature) * progress
+            }
+            TemperatureSchedule::Geometric => {
+                self.initial_temperature
+                    * (self.final_temperature / self.initial_temperature).powf(progress)
+            }
+        }
+    }
+}
+
+// Searches for the key maximizing the fitness of the whole plaintext by simulated annealing,
+// starting from the given key and returning the best key seen. Locked positions keep
+// their letters.
+pub(crate) fn anneal_key(
+    ciphertext: &str,
+    key: &str,
+    fitness: Fitness,
+    locks: &KeyLocks,
+    config: &AnnealingConfig,
+) -> Result<String> {
+    validate_key(key)?;
+
+    let mut rng = seeded_rng(config.seed);
+
+    let mut key = locks.applied(key).into_bytes();
+    let free: Vec<usize> = (0..key.len())
+        .filter(|&position| !locks.is_locked(position))
+        .collect();
+    if free.is_empty() {
+        return Ok(String::from_utf8(key).expect("key is ASCII"));
+    }
+    let mut score = fitness.score_key(ciphertext, &key)?;
+    let mut best_key = key.clone();
+    let mut best_score = score;
+
+    for iteration in 0..config.iterations {
+        let temperature = config.temperature(iteration as f64 / config.iterations as f64);
+
+        // Change one random key letter
+        let position = free[rng.gen_range(0..free.len())];
+        let original = key[position];
+        key[position] = b'A' + rng.gen_range(0..26);
+        let candidate_score = fitness.score_key(ciphertext, &key)?;
+
+        // Always accept improvements, and worse keys with a probability that falls with the temperature
+        let delta = candidate_score - score;
+        if delta >= 0.0 || rng.gen::<f64>() < (delta / temperature).exp() {
+            score = candidate_score;
+            if score > best_score {
+                best_score = score;
+                best_key = key.clone();
+            }
+        } else {
+            key[position] = original;
+        }
+    }
+
+    let best_key = String::from_utf8(best_key).expect("key is ASCII");
+    debug!("Annealing: best key {} ({:.4})", best_key, best_score);
+    Ok(best_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    fn config(seed: u64) -> AnnealingConfig {
+        AnnealingConfig {
+            iterations: 5000,
+            seed: Some(seed),
+            ..AnnealingConfig::default()
+        }
+    }
+
+    #[test]
+    fn annealing_finds_the_key_from_a_wrong_start() {
+        let key = anneal_key(
+            &lemon(),
+            "AAAAA",
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            &config(1),
+        )
+        .unwrap();
+        assert_eq!(key, "LEMON");
+    }
+
+    #[test]
+    fn annealing_is_reproducible_and_keeps_locks() {
+        let mut locks = KeyLocks::new();
+        locks.lock(0, 'Q').unwrap();
+        let anneal = || anneal_key(&lemon(), "AAAAA", Fitness::Quadgram, &locks, &config(7));
+        let key = anneal().unwrap();
+        assert!(key.starts_with('Q'), "{}", key);
+        assert_eq!(anneal().unwrap(), key);
+    }
+
+    #[test]
+    fn schedules_run_from_the_initial_to_the_final_temperature() {
+        for schedule in [TemperatureSchedule::Linear, TemperatureSchedule::Geometric] {
+            let config = AnnealingConfig {
+                schedule,
+                ..AnnealingConfig::default()
+            };
+            assert_eq!(config.temperature(0.0), config.initial_temperature);
+            assert!((config.temperature(1.0) - config.final_temperature).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// src/autokey.rs
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use log::debug;
+
+use crate::decryption::{letters, plaintext_fitness, validate_key, KeyAdvance};
+use crate::error::Result;
+use crate::fitness::Fitness;
+use crate::hill_climbing::hill_climb;
+use crate::k_len_estimator::MIN_COLUMN_LETTERS;
+use crate::key_locks::KeyLocks;
+
+// Longest primer searched.
+pub(crate) const MAX_PRIMER_LENGTH: usize = 20;
+
+// Decrypts autokey ciphertext following a key advancement policy. The autokey key stream
+// only ever advances on letters, so the policy just decides whether other characters are kept.
+pub fn decrypt(ciphertext: &str, primer: &str, advance: KeyAdvance) -> Result<String> {
+    match advance {
+        KeyAdvance::Strip => autokey_decrypt(&letters(ciphertext), primer),
+        KeyAdvance::LettersOnly | KeyAdvance::AllCharacters => autokey_decrypt(ciphertext, primer),
+    }
+}
+
+// Encrypts plaintext with the autokey cipher following a key advancement policy.
+pub fn encrypt(plaintext: &str, primer: &str, advance: KeyAdvance) -> Result<String> {
+    match advance {
+        KeyAdvance::Strip => autokey_encrypt(&letters(plaintext), primer),
+        KeyAdvance::LettersOnly | KeyAdvance::AllCharacters => autokey_encrypt(plaintext, primer),
+    }
+}
+
+// Decrypts autokey Vigenère ciphertext, whose key is the primer followed by the plaintext
+// itself. The key advances only on letters, other characters pass through unchanged.
+pub(crate) fn autokey_decrypt(ciphertext: &str, primer: &str) -> Result<String> {
+    validate_key(primer)?;
+
+    // Key letters still to use, the primer first and then each recovered plaintext letter
+    let mut key: VecDeque<u8> = primer
+        .bytes()
+        .map(|k| k.to_ascii_uppercase() - b'A')
+        .collect();
+    Ok(ciphertext
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                let k = key.pop_front().expect("key holds the primer length");
+                let p = (c as u8 - base + 26 - k) % 26;
+                key.push_back(p);
+                (p + base) as char
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Encrypts plaintext with the autokey Vigenère cipher, the inverse of `autokey_decrypt`.
+pub(crate) fn autokey_encrypt(plaintext: &str, primer: &str) -> Result<String> {
+    validate_key(primer)?;
+
+    let mut key: VecDeque<u8> = primer
+        .bytes()
+        .map(|k| k.to_ascii_uppercase() - b'A')
+        .collect();
+    Ok(plaintext
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                let k = key.pop_front().expect("key holds the primer length");
+                let p = c as u8 - base;
+                key.push_back(p);
+                ((p + k) % 26 + base) as char
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Finds the best primer of the given length, or of every length the text is long enough
+// for, returning each with its plaintext fitness from best to worst. Letters are the
+// uppercase ciphertext letters.
+pub(crate) fn crack_autokey(
+    letters: &str,
+    primer_length: Option<usize>,
+    fitness: Fitness,
+    locks: &KeyLocks,
+    refine: bool,
+) -> Result<Vec<(String, f64)>> {
+    let ciphertext: Vec<u8> = letters
+        .bytes()
+        .filter(u8::is_ascii_uppercase)
+        .map(|c| c - b'A')
+        .collect();
+    let lengths = match primer_length.or(locks.length()) {
+        Some(length) => length..=length,
+        None => 1..=(ciphertext.len() / MIN_COLUMN_LETTERS).clamp(1, MAX_PRIMER_LENGTH),
+    };
+
+    let mut primers = Vec::new();
+    for length in lengths {
+        let mut primer = guess_primer(&ciphertext, length).into_bytes();
+        locks.apply(&mut primer);
+        let primer = String::from_utf8(primer).expect("primer is ASCII");
+        let primer = if refine {
+            let alphabet: Vec<u8> = (b'A'..=b'Z').collect();
+            hill_climb(letters, &primer, fitness, locks, &alphabet, autokey_decrypt)?
+        } else {
+            primer
+        };
+
+        let score = fitness.score_decryption(&autokey_decrypt(letters, &primer)?, length);
+        debug!(
+            "Autokey primer length {}: {} ({:.4})",
+            length, primer, score
+        );
+        primers.push((primer, score));
+    }
+
+    primers.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    Ok(primers)
+}
+
+// Picks each primer letter by chi-squared. A primer letter alone decides every plaintext
+// letter in its column, as each one is the key for the letter a primer length further on.
+fn guess_primer(ciphertext: &[u8], length: usize) -> String {
+    (0..length)
+        .map(|i| {
+            let best_shift = (0..26)
+                .max_by(|&a, &b| {
+                    let a = plaintext_fitness(&decrypt_column(ciphertext, length, i, a));
+                    let b = plaintext_fitness(&decrypt_column(ciphertext, length, i, b));
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                })
+                .unwrap();
+            (b'A' + best_shift) as char
+        })
+        .collect()
+}
+
+// Plaintext of one primer column under the given primer letter.
+fn decrypt_column(ciphertext: &[u8], length: usize, column: usize, shift: u8) -> String {
+    let mut key = shift;
+    ciphertext
+        .iter()
+        .skip(column)
+        .step_by(length)
+        .map(|&c| {
+            let p = (c + 26 - key) % 26;
+            key = p;
+            (b'A' + p) as char
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::tests::assert_round_trips;
+    use crate::decryption::vigenere_decrypt;
+
+    // Letters of a known English plaintext, the Vigenère sample solved by its file name key.
+    fn sample_letters() -> String {
+        let ciphertext = letters(include_str!("../input/BJIE.txt")).to_ascii_uppercase();
+        vigenere_decrypt(&ciphertext, "BJIE").unwrap()
+    }
+
+    #[test]
+    fn autokey_encrypts_the_textbook_example() {
+        assert_eq!(
+            autokey_encrypt("ATTACKATDAWN", "QUEENLY").unwrap(),
+            "QNXEPVYTWTWP"
+        );
+    }
+
+    #[test]
+    fn decrypt_inverts_encrypt_for_every_policy() {
+        assert_round_trips(
+            "autokey",
+            |text, advance| encrypt(text, "QUEENLY", advance),
+            |text, advance| decrypt(text, "QUEENLY", advance),
+        );
+    }
+
+    #[test]
+    fn crack_autokey_recovers_the_primer() {
+        let ciphertext = autokey_encrypt(&sample_letters(), "QUEENLY").unwrap();
+        let primers = crack_autokey(
+            &ciphertext,
+            Some(7),
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(primers[0].0, "QUEENLY");
+    }
+}
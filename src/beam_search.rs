@@ -0,0 +1,179 @@
+// src/beam_search.rs
+use log::debug;
+
+use crate::decryption::{column_shifts, vigenere_decrypt};
+use crate::error::{CrackError, Result};
+use crate::fitness::Fitness;
+use crate::key_locks::KeyLocks;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamConfig {
+    // Lowest chi-squared shifts considered for each column.
+    pub shifts_per_column: usize,
+    // Partial keys kept after each column.
+    pub beam_width: usize,
+    // Full keys returned.
+    pub keys: usize,
+}
+
+impl Default for BeamConfig {
+    fn default() -> Self {
+        BeamConfig {
+            shifts_per_column: 3,
+            beam_width: 50,
+            keys: 5,
+        }
+    }
+}
+
+// A key prefix with its language model score and summed column chi-squared.
+#[derive(Debug, Clone)]
+struct PartialKey {
+    shifts: Vec<usize>,
+    score: f64,
+    chi_squared: f64,
+}
+
+// Searches the combinations of each column's best shifts for the keys whose plaintexts
+// score best under the fitness function, returning up to `config.keys` keys with their
+// scores from best to worst. Locked columns only take their locked shift.
+pub(crate) fn beam_search_keys(
+    ciphertext: &str,
+    key_length: usize,
+    fitness: Fitness,
+    locks: &KeyLocks,
+    config: &BeamConfig,
+) -> Result<Vec<(String, f64)>> {
+    if key_length == 0 {
+        return Err(CrackError::InvalidKey(String::new()));
+    }
+
+    let mut columns = column_shifts(ciphertext, key_length, config.shifts_per_column);
+    for (position, shifts) in columns.iter_mut().enumerate() {
+        if let Some(letter) = locks.letter(position) {
+            *shifts = vec![((letter - b'A') as usize, 0.0)];
+        }
+    }
+    let chars: Vec<char> = ciphertext.chars().collect();
+
+    let mut beam = vec![PartialKey {
+        shifts: Vec::new(),
+        score: 0.0,
+        chi_squared: 0.0,
+    }];
+    for (column, shifts) in columns.iter().enumerate() {
+        let mut extended: Vec<PartialKey> = beam
+            .iter()
+            .flat_map(|partial| {
+                shifts.iter().map(move |&(shift, chi_squared)| {
+                    let mut shifts = partial.shifts.clone();
+                    shifts.push(shift);
+                    (shifts, partial.chi_squared + chi_squared)
+                })
+            })
+            .map(|(shifts, chi_squared)| PartialKey {
+                score: score_prefix(&chars, key_length, &shifts, fitness),
+                shifts,
+                chi_squared,
+            })
+            .collect();
+
+        // Prefixes too short to hold an n-gram tie on score, so fall back to chi-squared
+        extended.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(a.chi_squared.total_cmp(&b.chi_squared))
+        });
+        extended.truncate(config.beam_width.max(1));
+        debug!(
+            "Beam column {}: {} prefixes, best score {:.4}",
+            column,
+            extended.len(),
+            extended[0].score
+        );
+        beam = extended;
+    }
+
+    // Rescore the complete keys on the whole plaintext, where n-grams cross key periods
+    let mut keys = beam
+        .into_iter()
+        .map(|partial| {
+            let key: String = partial
+                .shifts
+                .iter()
+                .map(|&shift| (b'A' + shift as u8) as char)
+                .collect();
+            let score = fitness.score(&vigenere_decrypt(ciphertext, &key)?);
+            Ok((key, score))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    keys.sort_by(|a, b| b.1.total_cmp(&a.1));
+    keys.truncate(config.keys.max(1));
+    Ok(keys)
+}
+
+// Scores the plaintext recovered by a key prefix. Each period only yields a run of letters
+// as long as the prefix, so the runs are scored separately.
+fn score_prefix(chars: &[char], key_length: usize, shifts: &[usize], fitness: Fitness) -> f64 {
+    let fragments = chars.chunks(key_length).map(|period| {
+        period
+            .iter()
+            .zip(shifts)
+            .map(|(&c, &shift)| {
+                if c.is_ascii_alphabetic() {
+                    let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                    ((c as u8 - base + 26 - shift as u8) % 26 + base) as char
+                } else {
+                    c
+                }
+            })
+            .collect::<String>()
+    });
+
+    match fitness {
+        // Letter frequencies need no context, but are unreliable on a few letters at a time
+        Fitness::Unigram => fitness.score(&fragments.collect::<String>()),
+        _ => fragments.map(|fragment| fitness.score(&fragment)).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    #[test]
+    fn beam_search_ranks_the_key_first() {
+        let keys = beam_search_keys(
+            &lemon(),
+            5,
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            &BeamConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(keys[0].0, "LEMON");
+        assert!(keys.len() <= BeamConfig::default().keys);
+        assert!(keys.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn beam_search_keeps_locked_columns() {
+        let mut locks = KeyLocks::new();
+        locks.lock(1, 'X').unwrap();
+        let keys = beam_search_keys(
+            &lemon(),
+            5,
+            Fitness::Quadgram,
+            &locks,
+            &BeamConfig::default(),
+        )
+        .unwrap();
+        assert!(keys.iter().all(|(key, _)| key.as_bytes()[1] == b'X'));
+    }
+}
@@ -0,0 +1,159 @@
+// src/cipher_variant.rs
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::decryption::{inverse_key, KeyAdvance};
+use crate::error::{CrackError, Result};
+use crate::porta;
+
+// Relation between plaintext p, ciphertext c and key letter k of a periodic polyalphabetic
+// cipher. Every variant is cracked as a Vigenère cipher on an equivalent ciphertext and key:
+// Beaufort ciphertext is mirrored (c -> -c) and its key inverted (k -> -k), while Variant
+// Beaufort only inverts the key. Mirroring permutes each column's letters, so the IC,
+// Kasiski and key length estimation are the same for every variant. Gronsfeld is Vigenère
+// restricted to the shifts 0-9, written as a key of digits. Porta swaps the halves A-M and
+// N-Z under one of 13 tableaux and has no Vigenère equivalent, so its text and key pass
+// through unchanged and the cracker solves its columns with the tableaux instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CipherVariant {
+    // p = c - k
+    #[default]
+    Vigenere,
+    // p = k - c
+    Beaufort,
+    // p = c + k
+    VariantBeaufort,
+    // p = c - k with k a digit
+    Gronsfeld,
+    // p = c under the reciprocal tableau of the key letter pair
+    Porta,
+}
+
+impl FromStr for CipherVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vigenere" | "vigenère" => Ok(CipherVariant::Vigenere),
+            "beaufort" => Ok(CipherVariant::Beaufort),
+            "variant-beaufort" => Ok(CipherVariant::VariantBeaufort),
+            "gronsfeld" => Ok(CipherVariant::Gronsfeld),
+            "porta" => Ok(CipherVariant::Porta),
+            _ => Err(format!("unknown cipher variant {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for CipherVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherVariant::Vigenere => write!(f, "Vigenère"),
+            CipherVariant::Beaufort => write!(f, "Beaufort"),
+            CipherVariant::VariantBeaufort => write!(f, "Variant Beaufort"),
+            CipherVariant::Gronsfeld => write!(f, "Gronsfeld"),
+            CipherVariant::Porta => write!(f, "Porta"),
+        }
+    }
+}
+
+impl CipherVariant {
+    pub const ALL: [CipherVariant; 5] = [
+        CipherVariant::Vigenere,
+        CipherVariant::Beaufort,
+        CipherVariant::VariantBeaufort,
+        CipherVariant::Gronsfeld,
+        CipherVariant::Porta,
+    ];
+
+    // Number of shifts a key letter can take, counted from A.
+    pub fn key_shifts(self) -> usize {
+        match self {
+            CipherVariant::Gronsfeld => 10,
+            CipherVariant::Vigenere
+            | CipherVariant::Beaufort
+            | CipherVariant::VariantBeaufort
+            | CipherVariant::Porta => 26,
+        }
+    }
+
+    // Ciphertext whose Vigenère decryption under `vigenere_key` gives this variant's plaintext.
+    pub fn vigenere_text(self, ciphertext: &str) -> String {
+        match self {
+            CipherVariant::Beaufort => mirror_letters(ciphertext),
+            CipherVariant::Vigenere
+            | CipherVariant::VariantBeaufort
+            | CipherVariant::Gronsfeld
+            | CipherVariant::Porta => ciphertext.to_string(),
+        }
+    }
+
+    // Vigenère key equivalent to a key of this variant. Gronsfeld digits become the letters
+    // A-J, while letters pass through.
+    pub fn vigenere_key(self, key: &str) -> String {
+        match self {
+            CipherVariant::Vigenere | CipherVariant::Porta => key.to_ascii_uppercase(),
+            CipherVariant::Beaufort | CipherVariant::VariantBeaufort => inverse_key(key),
+            CipherVariant::Gronsfeld => key
+                .chars()
+                .map(|k| match k.to_digit(10) {
+                    Some(digit) => (b'A' + digit as u8) as char,
+                    None => k.to_ascii_uppercase(),
+                })
+                .collect(),
+        }
+    }
+
+    // Key of this variant equivalent to a Vigenère key, undoing `vigenere_key`. Inverting a
+    // key is its own inverse, while Gronsfeld letters A-J become digits.
+    pub fn variant_key(self, vigenere_key: &str) -> String {
+        match self {
+            CipherVariant::Gronsfeld => vigenere_key
+                .chars()
+                .map(|k| match k.to_ascii_uppercase() {
+                    k @ 'A'..='J' => (b'0' + (k as u8 - b'A')) as char,
+                    k => k,
+                })
+                .collect(),
+            _ => self.vigenere_key(vigenere_key),
+        }
+    }
+
+    // Decrypts the ciphertext under the key, advancing the key following the policy.
+    pub fn decrypt(self, ciphertext: &str, key: &str, advance: KeyAdvance) -> Result<String> {
+        if self == CipherVariant::Porta {
+            return porta::decrypt(ciphertext, key, advance);
+        }
+        advance.decrypt(&self.vigenere_text(ciphertext), &self.vigenere_key(key))
+    }
+
+    // Encrypts the plaintext under the key, advancing the key following the policy.
+    // Gronsfeld keys must be digits.
+    pub fn encrypt(self, plaintext: &str, key: &str, advance: KeyAdvance) -> Result<String> {
+        if self == CipherVariant::Gronsfeld && !key.chars().all(|k| k.is_ascii_digit()) {
+            return Err(CrackError::InvalidKey(key.to_string()));
+        }
+        // Porta is reciprocal
+        if self == CipherVariant::Porta {
+            return porta::decrypt(plaintext, key, advance);
+        }
+        let ciphertext = advance.encrypt(plaintext, &self.vigenere_key(key))?;
+        Ok(self.vigenere_text(&ciphertext))
+    }
+}
+
+// Maps every letter to its additive inverse (A stays A, B <-> Z, ...), keeping its case.
+fn mirror_letters(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                (base + (26 - (c as u8 - base)) % 26) as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
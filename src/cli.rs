@@ -0,0 +1,370 @@
+// src/cli.rs
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use clap::error::ErrorKind;
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use vigenere_cracker::{
+    CipherVariant, Fitness, KeyAdvance, KeyLengthEstimationStrategy, KeyLocks, KeyStyle,
+    QuagmireKind, TemperatureSchedule,
+};
+
+use crate::output::OutputFormat;
+
+#[derive(Parser)]
+#[command(
+    name = "vigenere_cracker",
+    version,
+    about = "Cracks text encrypted with the Vigenère cipher"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Format of the results written to standard output
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Show more diagnostics (-v timings, -vv debug output)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Hide all diagnostics, including errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Crack one or more ciphertexts
+    Crack(Box<CrackArgs>),
+    /// Encrypt one or more plaintexts
+    Encrypt(EncryptArgs),
+    /// Generate random keys
+    Keygen(KeygenArgs),
+}
+
+#[derive(Args)]
+pub struct CrackArgs {
+    /// Ciphertext files to crack
+    #[arg(required_unless_present_any = ["stdin", "dir"])]
+    pub files: Vec<PathBuf>,
+
+    /// Read a single ciphertext from standard input
+    #[arg(long, conflicts_with_all = ["files", "dir"])]
+    pub stdin: bool,
+
+    /// Crack every file in the given directory
+    #[arg(long, value_name = "PATH")]
+    pub dir: Option<PathBuf>,
+
+    /// Key length strategies to combine: autocorrelation, ic, friedman, gcd
+    #[arg(long = "strategy", value_name = "NAME", value_delimiter = ',')]
+    pub strategies: Vec<KeyLengthEstimationStrategy>,
+
+    /// Crack under the top N key lengths and their divisors and multiples, keeping the best plaintext
+    #[arg(long, value_name = "N")]
+    pub retry: Option<usize>,
+
+    /// Plaintext fitness function: unigram, bigram, trigram or quadgram
+    #[arg(long, value_name = "NAME", default_value = "quadgram")]
+    pub fitness: Fitness,
+
+    /// Skip hill-climbing refinement of the guessed key
+    #[arg(long)]
+    pub no_refine: bool,
+
+    /// Also try every word of this wordlist as the key, reporting the best separately
+    #[arg(long, value_name = "PATH")]
+    pub wordlist: Option<PathBuf>,
+
+    /// Also try every pair of wordlist words joined together as the key
+    #[arg(long, requires = "wordlist")]
+    pub word_pairs: bool,
+
+    /// Known plaintext word to slide across the ciphertext, reporting the keys it implies
+    #[arg(long, value_name = "WORD")]
+    pub crib: Option<String>,
+
+    /// Key pattern with known letters and ? for unknown ones, e.g. L?MON, fixing the key length
+    #[arg(long, value_name = "PATTERN", value_parser = KeyLocks::from_pattern)]
+    pub key_pattern: Option<KeyLocks>,
+
+    /// Lock key letters at zero-based positions, e.g. 0=L,2=M
+    #[arg(long, value_name = "POS=LETTER", value_delimiter = ',', value_parser = parse_fix)]
+    pub fix: Vec<(usize, char)>,
+
+    /// Characters that consume key letters: letters (default), all, or strip to drop non-letters
+    #[arg(long, value_name = "POLICY", default_value = "letters")]
+    pub key_advance: KeyAdvance,
+
+    /// Cipher variant to crack, or auto to pick the one whose plaintext scores best
+    #[arg(long, value_enum, default_value_t = VariantChoice::Vigenere)]
+    pub variant: VariantChoice,
+
+    /// Crack an autokey cipher, whose key is a primer followed by the plaintext itself
+    #[arg(long, conflicts_with_all = ["variant", "retry", "solver", "wordlist", "crib"])]
+    pub autokey: bool,
+
+    /// Crack a keyed-alphabet Quagmire cipher of this type (1-4), recovering its alphabet
+    /// keywords and indicator key
+    #[arg(
+        long,
+        value_name = "TYPE",
+        conflicts_with_all = ["autokey", "variant", "solver", "wordlist", "crib", "key_pattern", "fix"]
+    )]
+    pub quagmire: Option<QuagmireKind>,
+
+    /// Period of the Quagmire cipher, estimated like a key length when not given
+    #[arg(long, value_name = "N", requires = "quagmire")]
+    pub period: Option<NonZeroUsize>,
+
+    /// Random restarts of the Quagmire alphabet search, 16 by default
+    #[arg(long, value_name = "N", requires = "quagmire")]
+    pub restarts: Option<usize>,
+
+    /// Method used to find the key once its length is estimated
+    #[arg(long, value_enum, default_value_t = SolverKind::ChiSquared)]
+    pub solver: SolverKind,
+
+    /// Iterations of the annealing solver
+    #[arg(long, value_name = "N")]
+    pub iterations: Option<usize>,
+
+    /// Starting temperature of the annealing solver
+    #[arg(long, value_name = "T")]
+    pub initial_temperature: Option<f64>,
+
+    /// Temperature the annealing solver cools down to
+    #[arg(long, value_name = "T")]
+    pub final_temperature: Option<f64>,
+
+    /// Cooling schedule of the annealing solver: linear or geometric
+    #[arg(long, value_name = "NAME")]
+    pub schedule: Option<TemperatureSchedule>,
+
+    /// Keys per generation of the genetic solver
+    #[arg(long, value_name = "N")]
+    pub population: Option<usize>,
+
+    /// Generations of the genetic solver
+    #[arg(long, value_name = "N")]
+    pub generations: Option<usize>,
+
+    /// Chance that the genetic solver recombines two parents
+    #[arg(long, value_name = "RATE")]
+    pub crossover_rate: Option<f64>,
+
+    /// Chance that the genetic solver mutates each letter of an offspring
+    #[arg(long, value_name = "RATE")]
+    pub mutation_rate: Option<f64>,
+
+    /// Fittest keys the genetic solver carries over unchanged each generation
+    #[arg(long, value_name = "N")]
+    pub elitism: Option<usize>,
+
+    /// Lowest chi-squared shifts the beam solver considers per column
+    #[arg(long, value_name = "K")]
+    pub beam_shifts: Option<usize>,
+
+    /// Partial keys the beam solver keeps after each column
+    #[arg(long, value_name = "N")]
+    pub beam_width: Option<usize>,
+
+    /// Full keys the beam solver returns, reported as runners-up
+    #[arg(long, value_name = "M")]
+    pub beam_keys: Option<usize>,
+
+    /// Seed for randomized solvers, making runs reproducible
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct EncryptArgs {
+    /// Plaintext files to encrypt, standard input when none are given
+    pub files: Vec<PathBuf>,
+
+    /// Key to encrypt with
+    #[arg(
+        long,
+        required_unless_present = "random_key",
+        conflicts_with = "random_key"
+    )]
+    pub key: Option<String>,
+
+    /// Encrypt every input with its own random key of this length
+    #[arg(long, value_name = "LENGTH")]
+    pub random_key: Option<NonZeroUsize>,
+
+    /// Style of random keys: word (letters following English bigrams), uniform, or digits for
+    /// Gronsfeld, whose random keys are always digits
+    #[arg(long, value_name = "STYLE", default_value = "word")]
+    pub key_style: KeyStyle,
+
+    /// Characters that consume key letters: letters (default), all, or strip to drop non-letters
+    #[arg(long, value_name = "POLICY", default_value = "letters")]
+    pub key_advance: KeyAdvance,
+
+    /// Cipher variant: vigenere, beaufort, variant-beaufort, gronsfeld (digit key) or porta
+    #[arg(long, value_name = "NAME", default_value = "vigenere")]
+    pub variant: CipherVariant,
+
+    /// Encrypt with the autokey cipher, using the key as the primer
+    #[arg(long, conflicts_with = "variant")]
+    pub autokey: bool,
+
+    /// Encrypt with a Quagmire cipher of this type (1-4), using the key as the indicator
+    #[arg(
+        long,
+        value_name = "TYPE",
+        conflicts_with_all = ["autokey", "variant"],
+        requires = "alphabet_key"
+    )]
+    pub quagmire: Option<QuagmireKind>,
+
+    /// Keyword of the keyed alphabet, the plaintext alphabet for Quagmire IV
+    #[arg(long, value_name = "KEYWORD", requires = "quagmire")]
+    pub alphabet_key: Option<String>,
+
+    /// Keyword of the Quagmire IV ciphertext alphabet
+    #[arg(long, value_name = "KEYWORD", requires = "quagmire")]
+    pub cipher_alphabet_key: Option<String>,
+
+    /// Seed for random keys, making runs reproducible
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct KeygenArgs {
+    /// Length of each key
+    #[arg(long, short = 'n')]
+    pub length: NonZeroUsize,
+
+    /// Number of keys to generate
+    #[arg(long, default_value_t = 1)]
+    pub count: usize,
+
+    /// Style of the keys: word (letters following English bigrams), uniform or digits
+    #[arg(long, value_name = "STYLE", default_value = "word")]
+    pub style: KeyStyle,
+
+    /// Seed for the keys, making runs reproducible
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl CrackArgs {
+    // Rejects option combinations clap cannot express, as they depend on an option's value.
+    pub fn check(&self) -> Result<(), clap::Error> {
+        self.check_porta()?;
+        // Gronsfeld keys are solved over the ten digit shifts alone
+        if self.variant == VariantChoice::Gronsfeld && self.solver != SolverKind::ChiSquared {
+            return Err(conflict("crack", "'--solver'", "'--variant gronsfeld'"));
+        }
+        // The autokey key stream is the plaintext's letters, so only letters can advance it
+        if self.autokey && self.key_advance == KeyAdvance::AllCharacters {
+            return Err(conflict("crack", "'--key-advance all'", "'--autokey'"));
+        }
+        Ok(())
+    }
+
+    // Rejects the options a Porta crack has no use for: Porta keys are solved over its
+    // tableaux, while the other solvers, dictionary screening and crib dragging all work on
+    // Caesar shifts.
+    fn check_porta(&self) -> Result<(), clap::Error> {
+        if self.variant != VariantChoice::Porta {
+            return Ok(());
+        }
+        let unsupported = [
+            (self.solver != SolverKind::ChiSquared, "--solver"),
+            (self.wordlist.is_some(), "--wordlist"),
+            (self.crib.is_some(), "--crib"),
+        ];
+        match unsupported.iter().find(|&&(given, _)| given) {
+            Some((_, flag)) => Err(conflict(
+                "crack",
+                &format!("'{}'", flag),
+                "'--variant porta'",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl EncryptArgs {
+    // Rejects option combinations clap cannot express, as they depend on an option's value.
+    pub fn check(&self) -> Result<(), clap::Error> {
+        // The autokey key stream is the plaintext's letters, so only letters can advance it
+        if self.autokey && self.key_advance == KeyAdvance::AllCharacters {
+            return Err(conflict("encrypt", "'--key-advance all'", "'--autokey'"));
+        }
+        // Only Quagmire IV keys its ciphertext alphabet with a keyword of its own
+        if self.cipher_alphabet_key.is_some() && self.quagmire != Some(QuagmireKind::Four) {
+            return Err(clap_error(
+                "encrypt",
+                ErrorKind::ArgumentConflict,
+                "the argument '--cipher-alphabet-key' can only be used with '--quagmire 4'",
+            ));
+        }
+        // Only Gronsfeld takes a key of digits
+        if self.key_style == KeyStyle::Digits && self.variant != CipherVariant::Gronsfeld {
+            return Err(clap_error(
+                "encrypt",
+                ErrorKind::MissingRequiredArgument,
+                "the argument '--key-style digits' requires '--variant gronsfeld'",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Usage error of a subcommand for an argument that cannot be used with another.
+fn conflict(subcommand: &str, argument: &str, other: &str) -> clap::Error {
+    clap_error(
+        subcommand,
+        ErrorKind::ArgumentConflict,
+        &format!("the argument {} cannot be used with {}", argument, other),
+    )
+}
+
+// Usage error of a subcommand, formatted like clap's own.
+fn clap_error(subcommand: &str, kind: ErrorKind, message: &str) -> clap::Error {
+    let mut command = Cli::command();
+    command.build();
+    command
+        .find_subcommand_mut(subcommand)
+        .expect("subcommand exists")
+        .error(kind, message)
+}
+
+// Parses a POS=LETTER key lock.
+fn parse_fix(s: &str) -> Result<(usize, char), String> {
+    let invalid = || format!("expected POS=LETTER, found {:?}", s);
+    let (position, letter) = s.split_once('=').ok_or_else(invalid)?;
+    let position = position.trim().parse().map_err(|_| invalid())?;
+    let mut letters = letter.trim().chars();
+    match (letters.next(), letters.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Ok((position, letter)),
+        _ => Err(invalid()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VariantChoice {
+    Auto,
+    Vigenere,
+    Beaufort,
+    VariantBeaufort,
+    Gronsfeld,
+    Porta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SolverKind {
+    ChiSquared,
+    Annealing,
+    Genetic,
+    Beam,
+}
@@ -0,0 +1,901 @@
+// src/cracker.rs
+use std::borrow::Cow;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+use log::{debug, info, warn};
+use serde::{Serialize, Serializer};
+
+use crate::autokey::{self, crack_autokey};
+use crate::cipher_variant::CipherVariant;
+use crate::crib::crib_drag;
+use crate::decryption::guess_key_within;
+use crate::decryption::{vigenere_decrypt, KeyAdvance};
+use crate::dictionary::{dictionary_attack, DictionaryConfig};
+use crate::error::{CrackError, Result};
+use crate::fitness::{confidence, Fitness};
+use crate::freq_analysis::{analyze_text, find_divisors};
+use crate::hill_climbing::hill_climb;
+use crate::k_len_estimator::{
+    estimate_key_length_using_multiple_strategies, KeyLengthCandidate, KeyLengthEstimationStrategy,
+    MIN_COLUMN_LETTERS,
+};
+use crate::key_locks::KeyLocks;
+use crate::porta::{self, porta_decrypt};
+use crate::quagmire::{crack_quagmire, QuagmireConfig, QuagmireKey};
+use crate::solver::KeySolver;
+
+// Serializes a duration as fractional milliseconds.
+fn serialize_millis<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+// Time spent in each stage of a crack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Timings {
+    #[serde(serialize_with = "serialize_millis")]
+    pub analysis: Duration,
+    #[serde(serialize_with = "serialize_millis")]
+    pub key_length_estimation: Duration,
+    #[serde(serialize_with = "serialize_millis")]
+    pub decryption: Duration,
+    #[serde(serialize_with = "serialize_millis")]
+    pub total: Duration,
+}
+
+// Largest multiple of a candidate key length tried when retrying.
+const MAX_RETRY_MULTIPLE: usize = 3;
+// Most runner-up decryptions kept.
+const MAX_ALTERNATIVES: usize = 5;
+// Top key lengths whose divisors are tried as dictionary key lengths.
+const DICTIONARY_KEY_LENGTHS: usize = 3;
+// Most crib placements reported.
+const CRIB_HITS: usize = 3;
+
+// A key tried while cracking and the plaintext it produces.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decryption {
+    pub key: String,
+    pub key_length: usize,
+    pub plaintext: String,
+    pub confidence: f64,
+    pub fitness: f64,
+}
+
+// A key proposed by placing the crib at an offset of the ciphertext.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CribHit {
+    pub offset: usize,
+    #[serde(flatten)]
+    pub decryption: Decryption,
+}
+
+// Outcome of cracking a single ciphertext.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CrackResult {
+    pub key: String,
+    pub plaintext: String,
+    pub key_length: usize,
+    pub variant: CipherVariant,
+    // Whether the key is an autokey primer followed by the plaintext rather than repeating.
+    pub autokey: bool,
+    // Keyed alphabets of a Quagmire crack, whose key is the indicator.
+    pub quagmire: Option<QuagmireKey>,
+    pub ic: f64,
+    // Confidence in the decryption as a percentage.
+    pub confidence: f64,
+    // Score of the plaintext under the cracker's fitness function, higher is better.
+    pub fitness: f64,
+    pub timings: Timings,
+    // Key lengths ranked from most to least likely, only the given one when it was specified.
+    // Autokey cracks rank primer lengths instead.
+    pub candidates: Vec<KeyLengthCandidate>,
+    // Runner-up decryptions from best to worst, empty unless retrying across key lengths
+    // or the solver finds several keys.
+    pub alternatives: Vec<Decryption>,
+    // Best decryptions under dictionary words from best to worst, empty without a dictionary.
+    pub dictionary_hits: Vec<Decryption>,
+    // Best keys proposed by the crib from best to worst, empty without a crib.
+    pub crib_hits: Vec<CribHit>,
+}
+
+impl fmt::Display for CrackResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Index of Coincidence: {:.6}", self.ic)?;
+        writeln!(f, "Estimated key length: {}", self.key_length)?;
+        if self.autokey {
+            writeln!(f, "Cipher variant: {} (autokey)", self.variant)?;
+        } else if let Some(quagmire) = &self.quagmire {
+            writeln!(f, "Cipher variant: {}", quagmire)?;
+        } else {
+            writeln!(f, "Cipher variant: {}", self.variant)?;
+        }
+        writeln!(f, "Decrypted key: {}", self.key)?;
+        write!(
+            f,
+            "Decrypted text with confidence {:.2}%: {}",
+            self.confidence, self.plaintext
+        )?;
+        for alternative in &self.alternatives {
+            write!(
+                f,
+                "\nRunner-up key: {} (length {}, fitness {:.2})",
+                alternative.key, alternative.key_length, alternative.fitness
+            )?;
+        }
+        for hit in &self.dictionary_hits {
+            write!(
+                f,
+                "\nDictionary key: {} (fitness {:.2}, confidence {:.2}%)",
+                hit.key, hit.fitness, hit.confidence
+            )?;
+        }
+        for hit in &self.crib_hits {
+            write!(
+                f,
+                "\nCrib key: {} (offset {}, fitness {:.2}, confidence {:.2}%)",
+                hit.decryption.key, hit.offset, hit.decryption.fitness, hit.decryption.confidence
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Reduces a key that repeats a shorter key to that shorter key.
+fn shortest_period(key: &str) -> &str {
+    let bytes = key.as_bytes();
+    (1..bytes.len())
+        .filter(|&period| bytes.len().is_multiple_of(period))
+        .find(|&period| (period..bytes.len()).all(|i| bytes[i] == bytes[i - period]))
+        .map_or(key, |period| &key[..period])
+}
+
+// Orders decryptions by best fitness first, preferring shorter keys on ties, and keeps
+// the best along with the runners-up reported.
+fn sort_decryptions(decryptions: &mut Vec<Decryption>) {
+    decryptions.sort_by(|a, b| {
+        b.fitness
+            .total_cmp(&a.fitness)
+            .then(a.key_length.cmp(&b.key_length))
+    });
+    decryptions.truncate(MAX_ALTERNATIVES + 1);
+}
+
+// Logs how long each stage of a crack took.
+fn log_timings(timings: &Timings) {
+    for (name, duration) in [
+        ("Analyze text", timings.analysis),
+        ("Estimate key length", timings.key_length_estimation),
+        ("Decrypt Vigenere", timings.decryption),
+    ] {
+        info!(
+            "{} took {} seconds and {} milliseconds",
+            name,
+            duration.as_secs(),
+            duration.subsec_millis()
+        );
+    }
+
+    info!(
+        "Total decryption time: {} seconds and {} milliseconds",
+        timings.total.as_secs(),
+        timings.total.subsec_millis()
+    );
+}
+
+// Entry point for cracking Vigenère ciphertexts with a fixed configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cracker {
+    // Strategies combined when estimating the key length.
+    pub strategies: Vec<KeyLengthEstimationStrategy>,
+    // Skips key length estimation when set.
+    pub key_length: Option<usize>,
+    // Skips key guessing when set.
+    pub key: Option<String>,
+    // Bonus per repeated Kasiski candidate when scoring key lengths, in sixteenths of the
+    // agreement of a key length that every candidate bears out.
+    pub frequency_multiplier: f64,
+    // Cracks under the top N key lengths and their divisors and multiples, keeping the
+    // decryption with the best plaintext fitness.
+    pub retry_top: Option<usize>,
+    // Scores plaintexts when choosing between decryptions.
+    pub fitness: Fitness,
+    // Finds the key for each key length tried.
+    pub solver: KeySolver,
+    // Words tried as the key, reported separately from the statistical result.
+    pub dictionary: Option<DictionaryConfig>,
+    // Plaintext word slid across the ciphertext to propose keys, reported separately.
+    pub crib: Option<String>,
+    // Key letters the solver and refinement must keep. A pattern also fixes the key length.
+    pub locks: KeyLocks,
+    // Hill climbs from each guessed key to fix columns the solver got wrong.
+    pub refine: bool,
+    // Cipher variant the ciphertext was encrypted with, detected by plaintext fitness when unset.
+    pub variant: Option<CipherVariant>,
+    // Which characters consume key letters, for the analysis as well as the plaintext.
+    pub key_advance: KeyAdvance,
+    // Cracks an autokey primer instead of a repeating key. Autokey keys only advance on letters.
+    pub autokey: bool,
+    // Cracks the keyed alphabets of a Quagmire cipher along with its indicator key.
+    pub quagmire: Option<QuagmireConfig>,
+}
+
+impl Default for Cracker {
+    fn default() -> Self {
+        Cracker {
+            strategies: vec![
+                KeyLengthEstimationStrategy::Autocorrelation,
+                KeyLengthEstimationStrategy::Gcd,
+            ],
+            key_length: None,
+            key: None,
+            frequency_multiplier: 5.0,
+            retry_top: None,
+            fitness: Fitness::default(),
+            solver: KeySolver::default(),
+            dictionary: None,
+            crib: None,
+            locks: KeyLocks::new(),
+            refine: true,
+            variant: Some(CipherVariant::default()),
+            key_advance: KeyAdvance::default(),
+            autokey: false,
+            quagmire: None,
+        }
+    }
+}
+
+impl Cracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Analyzes the ciphertext, estimates the key length and recovers the key and plaintext.
+    pub fn crack(&self, ciphertext: &str) -> Result<CrackResult> {
+        if self.autokey {
+            return self.crack_autokey(ciphertext);
+        }
+        if let Some(config) = &self.quagmire {
+            return self.crack_quagmire(ciphertext, config);
+        }
+
+        let mut timings = Timings::default();
+        let text = self.key_advance.analysis_text(ciphertext);
+        let (ic, possible_key_lengths, candidates) = self.estimate(&text, &mut timings)?;
+
+        // Time and run decryption under every variant tried, keeping the best plaintext
+        let start_time = Instant::now();
+        let variants = match self.variant {
+            Some(variant) => vec![variant],
+            // Variant Beaufort is Vigenère under the inverted key, so the two always tie
+            None => vec![CipherVariant::Vigenere, CipherVariant::Beaufort],
+        };
+        let mut detected: Option<(CipherVariant, Vec<Decryption>)> = None;
+        for variant in variants {
+            let decryptions = self
+                .for_variant(variant)
+                .key_decryptions(&variant.vigenere_text(&text), &candidates)?;
+            debug!("{} fitness: {:.4}", variant, decryptions[0].fitness);
+            if detected
+                .as_ref()
+                .is_none_or(|(_, best)| decryptions[0].fitness > best[0].fitness)
+            {
+                detected = Some((variant, decryptions));
+            }
+        }
+        let (variant, mut decryptions) = detected.expect("at least one variant is tried");
+        let cracker = self.for_variant(variant);
+        let text = variant.vigenere_text(&text);
+
+        let mut best = decryptions.remove(0);
+        cracker.warn_unused_locks(&best.key, true);
+        // Dictionary screening and crib dragging work on Caesar shifts, which Porta lacks
+        let porta = variant == CipherVariant::Porta;
+        if porta
+            && (cracker.dictionary.is_some()
+                || cracker.crib.is_some()
+                || cracker.solver != KeySolver::ChiSquared)
+        {
+            warn!(
+                "Porta keys are solved over its tableaux, ignoring the solver, dictionary and crib"
+            );
+        }
+        if variant == CipherVariant::Gronsfeld && cracker.solver != KeySolver::ChiSquared {
+            warn!("Gronsfeld keys are solved over the digit shifts, ignoring the solver");
+        }
+        let mut dictionary_hits = match &cracker.dictionary {
+            Some(dictionary) if !porta => {
+                cracker.dictionary_decryptions(&text, &candidates, dictionary)?
+            }
+            _ => Vec::new(),
+        };
+        let mut crib_hits = match &cracker.crib {
+            Some(crib) if !porta => {
+                // Kasiski lengths, unless the key length is known
+                let key_lengths = match self.key_length.or(self.locks.length()) {
+                    Some(key_length) => vec![key_length],
+                    None => possible_key_lengths,
+                };
+                cracker.crib_decryptions(&text, crib, &key_lengths)?
+            }
+            _ => Vec::new(),
+        };
+
+        // Render every decryption back into the variant's key and the ciphertext's layout and case
+        self.render(ciphertext, variant, &mut best)?;
+        for decryption in decryptions.iter_mut().chain(&mut dictionary_hits) {
+            self.render(ciphertext, variant, decryption)?;
+        }
+        for hit in &mut crib_hits {
+            self.render(ciphertext, variant, &mut hit.decryption)?;
+        }
+        timings.decryption = start_time.elapsed();
+
+        // Calculate total duration
+        timings.total = timings.analysis + timings.key_length_estimation + timings.decryption;
+        log_timings(&timings);
+
+        Ok(CrackResult {
+            key: best.key,
+            plaintext: best.plaintext,
+            key_length: best.key_length,
+            variant,
+            autokey: false,
+            quagmire: None,
+            ic,
+            confidence: best.confidence,
+            fitness: best.fitness,
+            timings,
+            candidates,
+            alternatives: decryptions,
+            dictionary_hits,
+            crib_hits,
+        })
+    }
+
+    // Key length given or implied by a pattern, which must leave every column two letters and
+    // hold every locked position.
+    fn fixed_key_length(&self, text: &str) -> Result<Option<usize>> {
+        let Some(key_length) = self.key_length.or(self.locks.length()) else {
+            return Ok(None);
+        };
+        let letters = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+        if key_length * 2 > letters {
+            return Err(CrackError::CiphertextTooShort {
+                letters,
+                minimum: key_length * 2,
+            });
+        }
+        if self.locks.span() > key_length {
+            let position = self.locks.span() - 1;
+            let letter = self.locks.letter(position).expect("span ends at a lock") as char;
+            return Err(CrackError::InvalidKey(format!("{}={}", position, letter)));
+        }
+        Ok(Some(key_length))
+    }
+
+    // Warns when the solved key is too short for some locked positions, which went unused.
+    // A repeating key still holds the locks past its end that agree with it repeated.
+    fn warn_unused_locks(&self, key: &str, repeats: bool) {
+        let key_length = key.len();
+        let unused = (key_length..self.locks.span()).any(|position| {
+            self.locks
+                .letter(position)
+                .is_some_and(|letter| !repeats || letter != key.as_bytes()[position % key_length])
+        });
+        if unused {
+            warn!(
+                "Key letters locked at positions {} and beyond lie past the key length {} and were ignored",
+                key_length, key_length
+            );
+        }
+    }
+
+    // Runs text analysis and key length estimation on the analysis text, returning the IC,
+    // the Kasiski key lengths and the ranked candidates.
+    fn estimate(
+        &self,
+        text: &str,
+        timings: &mut Timings,
+    ) -> Result<(f64, Vec<usize>, Vec<KeyLengthCandidate>)> {
+        // Time and run text analysis
+        let start_time = Instant::now();
+        let (ic, possible_key_lengths) = analyze_text(text)?;
+        timings.analysis = start_time.elapsed();
+        let key_length = self.fixed_key_length(text)?;
+
+        // Time and run key length estimation
+        let start_time = Instant::now();
+        let candidates = estimate_key_length_using_multiple_strategies(
+            &self.strategies,
+            possible_key_lengths.clone(),
+            text,
+            key_length,
+            self.frequency_multiplier,
+        )?;
+        timings.key_length_estimation = start_time.elapsed();
+        Ok((ic, possible_key_lengths, candidates))
+    }
+
+    // Recovers the keyed alphabets and indicator of a Quagmire cipher under the given or most
+    // likely period, or the top `retry_top` periods keeping the best plaintext. Every column is a
+    // simple substitution, so the IC and Kasiski estimate the period as for Vigenère.
+    fn crack_quagmire(&self, ciphertext: &str, config: &QuagmireConfig) -> Result<CrackResult> {
+        let mut timings = Timings::default();
+        let text = self.key_advance.analysis_text(ciphertext);
+        let (ic, _, candidates) = self.estimate(&text, &mut timings)?;
+
+        // Every column's substitution is solved from its own letters, so periods leaving too
+        // few letters per column are dropped before the search
+        let letter_count = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+        let periods: Vec<usize> = candidates
+            .iter()
+            .map(|candidate| candidate.key_length)
+            .filter(|&period| period * MIN_COLUMN_LETTERS <= letter_count)
+            .take(self.retry_top.unwrap_or(1).max(1))
+            .collect();
+        if periods.is_empty() {
+            return Err(CrackError::CiphertextTooShort {
+                letters: letter_count,
+                minimum: candidates[0].key_length * MIN_COLUMN_LETTERS,
+            });
+        }
+
+        // Time and run the alphabet search under every period tried
+        let start_time = Instant::now();
+        let mut keys = Vec::new();
+        for period in periods {
+            keys.push(crack_quagmire(&text, period, self.fitness, config)?);
+        }
+        keys.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut decryptions = keys
+            .iter()
+            .map(|(key, score)| {
+                let plaintext = key.decrypt(ciphertext, self.key_advance)?;
+                Ok(Decryption {
+                    key: key.indicator.clone(),
+                    key_length: key.indicator.len(),
+                    confidence: confidence(&plaintext),
+                    fitness: *score,
+                    plaintext,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let best = decryptions.remove(0);
+        timings.decryption = start_time.elapsed();
+
+        timings.total = timings.analysis + timings.key_length_estimation + timings.decryption;
+        log_timings(&timings);
+
+        Ok(CrackResult {
+            key: best.key,
+            plaintext: best.plaintext,
+            key_length: best.key_length,
+            variant: CipherVariant::Vigenere,
+            autokey: false,
+            quagmire: keys.into_iter().next().map(|(key, _)| key),
+            ic,
+            confidence: best.confidence,
+            fitness: best.fitness,
+            timings,
+            candidates,
+            alternatives: decryptions,
+            dictionary_hits: Vec::new(),
+            crib_hits: Vec::new(),
+        })
+    }
+
+    // Searches autokey primers instead of a repeating key. Kasiski and autocorrelation see no
+    // period in an autokey ciphertext, so primer lengths are ranked by plaintext fitness.
+    fn crack_autokey(&self, ciphertext: &str) -> Result<CrackResult> {
+        let mut timings = Timings::default();
+        let text = KeyAdvance::LettersOnly.analysis_text(ciphertext);
+
+        // Time and run text analysis
+        let start_time = Instant::now();
+        let (ic, _) = analyze_text(&text)?;
+        timings.analysis = start_time.elapsed();
+        self.fixed_key_length(&text)?;
+
+        // Time and run the primer search, every primer length taking the place of a key length
+        let start_time = Instant::now();
+        let primers = match &self.key {
+            Some(key) => {
+                let score = self
+                    .fitness
+                    .score_decryption(&autokey::autokey_decrypt(&text, key)?, key.len());
+                vec![(key.to_ascii_uppercase(), score)]
+            }
+            None => crack_autokey(
+                &text,
+                self.key_length,
+                self.fitness,
+                &self.locks,
+                self.refine,
+            )?,
+        };
+        let mut decryptions = primers
+            .iter()
+            .take(MAX_ALTERNATIVES + 1)
+            .map(|(primer, score)| {
+                let plaintext = autokey::decrypt(ciphertext, primer, self.key_advance)?;
+                Ok(Decryption {
+                    key: primer.clone(),
+                    key_length: primer.len(),
+                    confidence: confidence(&plaintext),
+                    fitness: *score,
+                    plaintext,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let best = decryptions.remove(0);
+        self.warn_unused_locks(&best.key, false);
+        timings.decryption = start_time.elapsed();
+
+        // Scores are penalized log10 likelihoods, so their differences give relative odds
+        let top = primers[0].1;
+        let total: f64 = primers
+            .iter()
+            .map(|(_, score)| 10f64.powf(score - top))
+            .sum();
+        let candidates = primers
+            .iter()
+            .map(|(primer, score)| KeyLengthCandidate {
+                key_length: primer.len(),
+                score: *score,
+                probability: 10f64.powf(score - top) / total,
+            })
+            .collect();
+
+        timings.total = timings.analysis + timings.decryption;
+        log_timings(&timings);
+
+        Ok(CrackResult {
+            key: best.key,
+            plaintext: best.plaintext,
+            key_length: best.key_length,
+            variant: CipherVariant::Vigenere,
+            autokey: true,
+            quagmire: None,
+            ic,
+            confidence: best.confidence,
+            fitness: best.fitness,
+            timings,
+            candidates,
+            alternatives: decryptions,
+            dictionary_hits: Vec::new(),
+            crib_hits: Vec::new(),
+        })
+    }
+
+    // Whether the ciphertext is solved with the Porta tableaux rather than as Vigenère.
+    fn is_porta(&self) -> bool {
+        self.variant == Some(CipherVariant::Porta)
+    }
+
+    // Shifts a key letter can take under the chosen variant, 26 unless it restricts them.
+    fn key_shifts(&self) -> usize {
+        self.variant.map_or(26, CipherVariant::key_shifts)
+    }
+
+    // Whether every letter of an equivalent Vigenère key is a shift the variant allows.
+    fn fits_key_shifts(&self, key: &str) -> bool {
+        key.bytes()
+            .all(|k| ((k.to_ascii_uppercase() - b'A') as usize) < self.key_shifts())
+    }
+
+    // Configuration cracking a variant as Vigenère, with the given key, locks and dictionary
+    // words converted to their equivalent Vigenère keys.
+    fn for_variant(&self, variant: CipherVariant) -> Cow<'_, Cracker> {
+        if matches!(variant, CipherVariant::Vigenere | CipherVariant::Porta) {
+            return Cow::Borrowed(self);
+        }
+
+        let mut cracker = self.clone();
+        cracker.key = self.key.as_deref().map(|key| variant.vigenere_key(key));
+        if variant != CipherVariant::Gronsfeld {
+            cracker.locks = self.locks.inverted();
+        }
+        if let Some(dictionary) = &mut cracker.dictionary {
+            for word in dictionary.words.iter_mut() {
+                *word = variant.vigenere_key(word);
+            }
+            dictionary.words.retain(|word| self.fits_key_shifts(word));
+        }
+        Cow::Owned(cracker)
+    }
+
+    // Decrypts the equivalent Vigenère text under the given key, the retried key lengths or
+    // the most likely key length, best plaintext first.
+    fn key_decryptions(
+        &self,
+        text: &str,
+        candidates: &[KeyLengthCandidate],
+    ) -> Result<Vec<Decryption>> {
+        match (self.retry_top, &self.key) {
+            (_, Some(key)) => Ok(vec![self.decryption(text, key.clone())?]),
+            // A given key length or pattern leaves no other lengths to retry
+            (Some(top), None) if self.key_length.or(self.locks.length()).is_none() => {
+                self.retry_decryptions(text, candidates, top)
+            }
+            (_, None) => {
+                let mut decryptions = Vec::new();
+                self.solve_key_length(
+                    text,
+                    candidates[0].key_length,
+                    &self.locks,
+                    &mut decryptions,
+                )?;
+                sort_decryptions(&mut decryptions);
+                Ok(decryptions)
+            }
+        }
+    }
+
+    // Converts a decryption's equivalent Vigenère key back to the variant's key and replaces
+    // its plaintext with the original ciphertext decrypted under it, keeping case along with
+    // line breaks and punctuation unless they are stripped.
+    fn render(
+        &self,
+        ciphertext: &str,
+        variant: CipherVariant,
+        decryption: &mut Decryption,
+    ) -> Result<()> {
+        decryption.key = variant.variant_key(&decryption.key);
+        decryption.plaintext = variant.decrypt(ciphertext, &decryption.key, self.key_advance)?;
+        Ok(())
+    }
+
+    // Decrypts with a key and scores the plaintext.
+    fn decryption(&self, ciphertext: &str, key: String) -> Result<Decryption> {
+        let plaintext = if self.is_porta() {
+            porta_decrypt(ciphertext, &key)?
+        } else {
+            vigenere_decrypt(ciphertext, &key)?
+        };
+        Ok(Decryption {
+            key_length: key.len(),
+            fitness: self.fitness.score_decryption(&plaintext, key.len()),
+            confidence: confidence(&plaintext),
+            key,
+            plaintext,
+        })
+    }
+
+    // Decrypts under the dictionary keys that fit the top key lengths, best plaintext first.
+    fn dictionary_decryptions(
+        &self,
+        ciphertext: &str,
+        candidates: &[KeyLengthCandidate],
+        dictionary: &DictionaryConfig,
+    ) -> Result<Vec<Decryption>> {
+        let key_lengths: Vec<usize> = candidates
+            .iter()
+            .take(DICTIONARY_KEY_LENGTHS)
+            .map(|candidate| candidate.key_length)
+            .collect();
+        dictionary_attack(ciphertext, Some(&key_lengths), self.fitness, dictionary)?
+            .into_iter()
+            .map(|(key, _)| self.decryption(ciphertext, key))
+            .collect()
+    }
+
+    // Decrypts under the keys the crib proposes, reduced to their shortest period,
+    // best plaintext first.
+    fn crib_decryptions(
+        &self,
+        ciphertext: &str,
+        crib: &str,
+        key_lengths: &[usize],
+    ) -> Result<Vec<CribHit>> {
+        crib_drag(ciphertext, crib, key_lengths, self.fitness, CRIB_HITS)?
+            .into_iter()
+            .map(|(offset, key, _)| (offset, shortest_period(&key).to_string()))
+            .filter(|(_, key)| self.fits_key_shifts(key))
+            .unique_by(|(_, key)| key.clone())
+            .map(|(offset, key)| {
+                Ok(CribHit {
+                    offset,
+                    decryption: self.decryption(ciphertext, key)?,
+                })
+            })
+            .collect()
+    }
+
+    // Refines a guessed key when enabled, reduced to its shortest period. A multiple of the
+    // true length yields the true key repeated, so its locks fold onto the shorter key.
+    fn refined(&self, ciphertext: &str, key: &str, locks: &KeyLocks) -> Result<String> {
+        let reduced = shortest_period(key);
+        if !self.refine {
+            return Ok(reduced.to_string());
+        }
+        let locks = locks.folded(key.len(), reduced.len());
+        let refined = if self.is_porta() {
+            hill_climb(
+                ciphertext,
+                reduced,
+                self.fitness,
+                &locks,
+                &porta::key_letters(),
+                porta::porta_decrypt,
+            )?
+        } else {
+            let alphabet: Vec<u8> = (b'A'..=b'Z').take(self.key_shifts()).collect();
+            hill_climb(
+                ciphertext,
+                reduced,
+                self.fitness,
+                &locks,
+                &alphabet,
+                vigenere_decrypt,
+            )?
+        };
+        Ok(shortest_period(&refined).to_string())
+    }
+
+    // Decrypts under the top key lengths and their divisors and multiples, best plaintext first.
+    // A length repeating a shorter top key length repeats its locks at every copy of that key.
+    fn retry_decryptions(
+        &self,
+        ciphertext: &str,
+        candidates: &[KeyLengthCandidate],
+        top: usize,
+    ) -> Result<Vec<Decryption>> {
+        let letter_count = ciphertext
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .count();
+        let top_lengths: Vec<usize> = candidates
+            .iter()
+            .take(top.max(1))
+            .map(|candidate| candidate.key_length)
+            .collect();
+        let key_lengths: Vec<usize> = top_lengths
+            .iter()
+            .flat_map(|&key_length| {
+                let mut related = vec![key_length];
+                related.extend(find_divisors(key_length));
+                related.extend((2..=MAX_RETRY_MULTIPLE).map(|multiple| key_length * multiple));
+                related
+            })
+            .filter(|&key_length| key_length * MIN_COLUMN_LETTERS <= letter_count)
+            .unique()
+            .collect();
+
+        // Shortest top key length that holds every lock and the key length repeats
+        let base = |key_length: usize| {
+            top_lengths
+                .iter()
+                .copied()
+                .filter(|&base| key_length.is_multiple_of(base) && base >= self.locks.span())
+                .min()
+                .unwrap_or(key_length)
+        };
+        let mut decryptions: Vec<Decryption> = Vec::new();
+        for key_length in key_lengths {
+            let locks = self.locks.repeated(base(key_length), key_length);
+            self.solve_key_length(ciphertext, key_length, &locks, &mut decryptions)?;
+        }
+        sort_decryptions(&mut decryptions);
+        Ok(decryptions)
+    }
+
+    // Adds the decryptions under every key the solver finds for a key length and its locks,
+    // skipping keys already tried. Keys restricted to fewer shifts, such as Gronsfeld digits,
+    // are guessed by chi-squared over the allowed shifts alone, and Porta keys over its tableaux.
+    fn solve_key_length(
+        &self,
+        ciphertext: &str,
+        key_length: usize,
+        locks: &KeyLocks,
+        decryptions: &mut Vec<Decryption>,
+    ) -> Result<()> {
+        let keys = if self.is_porta() {
+            vec![locks.applied(&porta::guess_key(ciphertext, key_length))]
+        } else if self.key_shifts() < 26 {
+            let key = guess_key_within(ciphertext, key_length, self.key_shifts());
+            vec![locks.applied(&key)]
+        } else {
+            self.solver
+                .solve_ranked(ciphertext, key_length, self.fitness, locks)?
+        };
+        for key in keys {
+            let key = self.refined(ciphertext, &key, locks)?;
+            if decryptions.iter().any(|decryption| decryption.key == key) {
+                continue;
+            }
+
+            let decryption = self.decryption(ciphertext, key)?;
+            debug!(
+                "Key length {}: {} ({:.4})",
+                key_length, decryption.key, decryption.fitness
+            );
+            decryptions.push(decryption);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vigenère sample whose file name is its key.
+    const LEMON: &str = include_str!("../input/LEMON.txt");
+
+    #[test]
+    fn retry_recovers_the_key_when_the_top_length_is_wrong() {
+        // The Friedman estimate alone ranks 8 first, with a multiple of the key length behind
+        let cracker = Cracker {
+            strategies: vec![KeyLengthEstimationStrategy::FriedmanTest],
+            ..Cracker::new()
+        };
+        assert_ne!(cracker.crack(LEMON).unwrap().key, "LEMON");
+
+        let cracker = Cracker {
+            retry_top: Some(3),
+            ..cracker
+        };
+        let result = cracker.crack(LEMON).unwrap();
+        assert_eq!(result.key, "LEMON");
+        assert!(!result.alternatives.is_empty());
+        assert!(result
+            .alternatives
+            .iter()
+            .all(|decryption| decryption.fitness <= result.fitness));
+    }
+
+    #[test]
+    fn retry_keeps_the_pattern_length() {
+        let cracker = Cracker {
+            locks: KeyLocks::from_pattern("Q?MON").unwrap(),
+            retry_top: Some(3),
+            ..Cracker::new()
+        };
+        let result = cracker.crack(LEMON).unwrap();
+        assert_eq!(result.key, "QEMON");
+        assert!(result
+            .alternatives
+            .iter()
+            .all(|decryption| decryption.key_length == 5));
+    }
+
+    #[test]
+    fn retry_repeats_locks_over_multiples_of_a_top_length() {
+        let mut locks = KeyLocks::new();
+        locks.lock(0, 'Q').unwrap();
+        let cracker = Cracker {
+            locks,
+            retry_top: Some(3),
+            ..Cracker::new()
+        };
+        assert_eq!(cracker.crack(LEMON).unwrap().key, "QEMON");
+    }
+
+    #[test]
+    fn locks_past_the_key_length_fold_onto_the_repeated_key() {
+        let mut locks = KeyLocks::new();
+        locks.lock(7, 'M').unwrap();
+        let cracker = Cracker {
+            locks,
+            retry_top: Some(3),
+            ..Cracker::new()
+        };
+        assert_eq!(cracker.crack(LEMON).unwrap().key, "LEMON");
+    }
+
+    #[test]
+    fn locked_letters_are_kept_by_refinement() {
+        let mut locks = KeyLocks::new();
+        locks.lock(2, 'A').unwrap();
+        let cracker = Cracker {
+            locks,
+            ..Cracker::new()
+        };
+        assert_eq!(cracker.crack(LEMON).unwrap().key, "LEAON");
+    }
+}
@@ -0,0 +1,132 @@
+// src/crib.rs
+use itertools::Itertools;
+use log::debug;
+
+use crate::decryption::column_chi_squared;
+use crate::dictionary::{rank_survivors, SCREENED_KEYS};
+use crate::error::{CrackError, Result};
+use crate::fitness::Fitness;
+
+// Slides a known plaintext word across the ciphertext and proposes a key of each candidate
+// length wherever the key fragment it implies repeats consistently with that length. Columns
+// the crib does not cover take their lowest chi-squared shift. Returns up to `hits` proposals
+// as the crib's offset, the key and its plaintext fitness, from best to worst.
+pub(crate) fn crib_drag(
+    ciphertext: &str,
+    crib: &str,
+    key_lengths: &[usize],
+    fitness: Fitness,
+    hits: usize,
+) -> Result<Vec<(usize, String, f64)>> {
+    let crib = crib.to_ascii_uppercase();
+    if crib.is_empty() || !crib.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(CrackError::InvalidCrib(crib));
+    }
+    let crib = crib.as_bytes();
+    let chars: Vec<char> = ciphertext.chars().collect();
+
+    let mut proposals: Vec<(usize, Vec<u8>, f64)> = Vec::new();
+    for key_length in key_lengths.iter().copied().filter(|&l| l > 0).unique() {
+        let table = column_chi_squared(ciphertext, key_length);
+        let best_shifts: Vec<u8> = table
+            .iter()
+            .map(|column| {
+                (0..26)
+                    .min_by(|&a, &b| column[a as usize].total_cmp(&column[b as usize]))
+                    .unwrap()
+            })
+            .collect();
+
+        for (offset, window) in chars.windows(crib.len()).enumerate() {
+            let Some(fragment) = key_fragment(window, crib) else {
+                continue;
+            };
+
+            // Fragment letters that fall on the same key position must agree
+            let mut shifts: Vec<Option<u8>> = vec![None; key_length];
+            let consistent = fragment.iter().enumerate().all(|(i, &shift)| {
+                let slot = &mut shifts[(offset + i) % key_length];
+                *slot.get_or_insert(shift) == shift
+            });
+            if !consistent {
+                continue;
+            }
+
+            let shifts: Vec<u8> = shifts
+                .iter()
+                .zip(&best_shifts)
+                .map(|(shift, &best)| shift.unwrap_or(best))
+                .collect();
+            let chi_squared = shifts
+                .iter()
+                .zip(&table)
+                .map(|(&shift, column)| column[shift as usize])
+                .sum();
+            proposals.push((offset, shifts, chi_squared));
+        }
+    }
+    debug!("Crib dragging: {} consistent placements", proposals.len());
+
+    // Cheaply rank the proposals by how well their shifts fit each column's letter frequencies
+    proposals.sort_by(|a, b| a.2.total_cmp(&b.2));
+    let screened: Vec<(usize, String)> = proposals
+        .into_iter()
+        .map(|(offset, shifts, _)| {
+            let key: String = shifts.iter().map(|&shift| (b'A' + shift) as char).collect();
+            (offset, key)
+        })
+        .unique_by(|(_, key)| key.clone())
+        .take(SCREENED_KEYS)
+        .collect();
+
+    rank_survivors(ciphertext, fitness, screened, hits)
+}
+
+// Shifts that turn the crib into the ciphertext window, or None when the window holds
+// anything but letters.
+fn key_fragment(window: &[char], crib: &[u8]) -> Option<Vec<u8>> {
+    window
+        .iter()
+        .zip(crib)
+        .map(|(&c, &p)| {
+            c.is_ascii_alphabetic()
+                .then(|| (c.to_ascii_uppercase() as u8 + 26 - p) % 26)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    #[test]
+    fn a_known_word_proposes_the_key_at_its_offset() {
+        let hits = crib_drag(&lemon(), "foolish", &[5], Fitness::Quadgram, 3).unwrap();
+        assert_eq!((hits[0].0, hits[0].1.as_str()), (13, "LEMON"));
+        assert!(hits.windows(2).all(|pair| pair[0].2 >= pair[1].2));
+    }
+
+    #[test]
+    fn the_key_fragment_fills_the_columns_it_covers() {
+        let fragment: Vec<char> = "PLQ".chars().collect();
+        assert_eq!(key_fragment(&fragment, b"ABC"), Some(vec![15, 10, 14]));
+        let window: Vec<char> = "P Q".chars().collect();
+        assert_eq!(key_fragment(&window, b"ABC"), None);
+    }
+
+    #[test]
+    fn cribs_must_be_letters() {
+        for crib in ["", "two words", "R2D2"] {
+            assert!(matches!(
+                crib_drag(&lemon(), crib, &[5], Fitness::Quadgram, 3),
+                Err(CrackError::InvalidCrib(_))
+            ));
+        }
+    }
+}
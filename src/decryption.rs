@@ -1,8 +1,11 @@
 // src/decryption.rs
-use crate::logger::log_debug;
+use crate::error::{CrackError, Result};
+use itertools::Itertools;
+use log::debug;
 use std::collections::HashMap;
+use std::str::FromStr;
 
-pub const ENGLISH_FREQUENCIES: [(char, f64); 26] = [
+pub(crate) const ENGLISH_FREQUENCIES: [(char, f64); 26] = [
     ('A', 0.08167),
     ('B', 0.01492),
     ('C', 0.02782),
@@ -31,9 +34,92 @@ pub const ENGLISH_FREQUENCIES: [(char, f64); 26] = [
     ('Z', 0.00074),
 ];
 
+// Which ciphertext characters consume a key letter, and what happens to the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyAdvance {
+    // Only letters consume key letters, other characters pass through unchanged.
+    #[default]
+    LettersOnly,
+    // Every character consumes a key letter, non-letters pass through unchanged.
+    AllCharacters,
+    // Only letters consume key letters and everything else is dropped from the plaintext.
+    Strip,
+}
+
+impl FromStr for KeyAdvance {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "letters" | "letters-only" => Ok(KeyAdvance::LettersOnly),
+            "all" | "all-characters" => Ok(KeyAdvance::AllCharacters),
+            "strip" => Ok(KeyAdvance::Strip),
+            _ => Err(format!("unknown key advancement policy {:?}", s)),
+        }
+    }
+}
+
+impl KeyAdvance {
+    // Uppercase text whose character positions line up with the key, for the statistics and
+    // the key search. Non-letters that consume a key letter become a single ASCII placeholder
+    // so character and byte positions agree.
+    pub fn analysis_text(self, ciphertext: &str) -> String {
+        match self {
+            KeyAdvance::LettersOnly | KeyAdvance::Strip => ciphertext
+                .chars()
+                .filter(char::is_ascii_alphabetic)
+                .map(|c| c.to_ascii_uppercase())
+                .collect(),
+            KeyAdvance::AllCharacters => ciphertext
+                .chars()
+                .map(|c| {
+                    if c.is_ascii() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '?'
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    // Decrypts the ciphertext under the key following this policy, keeping the case of letters.
+    pub fn decrypt(self, ciphertext: &str, key: &str) -> Result<String> {
+        match self {
+            KeyAdvance::LettersOnly => vigenere_decrypt_letters(ciphertext, key),
+            KeyAdvance::AllCharacters => vigenere_decrypt(ciphertext, key),
+            KeyAdvance::Strip => vigenere_decrypt(&letters(ciphertext), key),
+        }
+    }
+
+    // Encrypts the plaintext under the key following this policy, keeping the case of letters.
+    pub fn encrypt(self, plaintext: &str, key: &str) -> Result<String> {
+        match self {
+            KeyAdvance::LettersOnly => vigenere_encrypt_letters(plaintext, key),
+            KeyAdvance::AllCharacters => vigenere_encrypt(plaintext, key),
+            KeyAdvance::Strip => vigenere_encrypt(&letters(plaintext), key),
+        }
+    }
+}
+
+// The ASCII letters of a text.
+pub(crate) fn letters(text: &str) -> String {
+    text.chars().filter(char::is_ascii_alphabetic).collect()
+}
+
+// Checks that a key is non-empty and made only of ASCII letters.
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || !key.chars().all(|k| k.is_ascii_alphabetic()) {
+        return Err(CrackError::InvalidKey(key.to_string()));
+    }
+    Ok(())
+}
+
 // Decrypts Vigenère ciphertext using the provided key.
-fn vigenere_decrypt(ciphertext: &str, key: &str) -> String {
-    ciphertext
+pub(crate) fn vigenere_decrypt(ciphertext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+
+    Ok(ciphertext
         .chars()
         .zip(key.chars().cycle())
         .map(|(c, k)| {
@@ -42,7 +128,7 @@ fn vigenere_decrypt(ciphertext: &str, key: &str) -> String {
                 // Determine character base (lowercase or uppercase).
                 let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
                 let c = c as u8 - base;
-                let k = k as u8 - base;
+                let k = k.to_ascii_uppercase() as u8 - b'A';
                 // Decrypt the character using Vigenère decryption.
                 let decrypted = (c + 26 - k) % 26;
                 (decrypted + base) as char
@@ -50,13 +136,65 @@ fn vigenere_decrypt(ciphertext: &str, key: &str) -> String {
                 c
             }
         })
+        .collect())
+}
+
+// Decrypts Vigenère ciphertext using the provided key, advancing the key only on letters
+// so spaces, punctuation and line breaks pass through without consuming key letters.
+pub(crate) fn vigenere_decrypt_letters(ciphertext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+
+    let mut key_letters = key.bytes().map(|k| k.to_ascii_uppercase() - b'A').cycle();
+    Ok(ciphertext
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                let k = key_letters.next().expect("key is not empty");
+                ((c as u8 - base + 26 - k) % 26 + base) as char
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Key whose decryption undoes decryption under the given key, so encrypting is decrypting
+// under it.
+pub(crate) fn inverse_key(key: &str) -> String {
+    key.bytes()
+        .map(|k| {
+            if k.is_ascii_alphabetic() {
+                (b'A' + (26 - (k.to_ascii_uppercase() - b'A')) % 26) as char
+            } else {
+                k as char
+            }
+        })
         .collect()
 }
 
+// Encrypts plaintext with the Vigenère cipher, the inverse of `vigenere_decrypt`.
+pub(crate) fn vigenere_encrypt(plaintext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+    vigenere_decrypt(plaintext, &inverse_key(key))
+}
+
+// Encrypts plaintext advancing the key only on letters, the inverse of `vigenere_decrypt_letters`.
+pub(crate) fn vigenere_encrypt_letters(plaintext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+    vigenere_decrypt_letters(plaintext, &inverse_key(key))
+}
+
 // Computes the chi-squared test value for a given text, shift, and English frequencies.
 fn chi_squared_test(text: &str, shift: usize, english_frequencies: &[(char, f64)]) -> f64 {
-    let freq_map = character_frequency_f64(&text);
-    let total_chars = text.chars().count() as f64;
+    let freq_map = character_frequency_f64(text);
+    // Only letters are counted, whatever else the column holds
+    let total_chars = text.chars().filter(|c| c.is_ascii_alphabetic()).count() as f64;
+    // A column of only non-letters, possible when every character advances the key, fits
+    // every shift equally
+    if total_chars == 0.0 {
+        return 0.0;
+    }
 
     let mut chi_squared = 0.0;
     for (c, expected_freq) in english_frequencies.iter() {
@@ -69,10 +207,49 @@ fn chi_squared_test(text: &str, shift: usize, english_frequencies: &[(char, f64)
     chi_squared
 }
 
+// Scores how closely a plaintext follows English letter frequencies, higher is better.
+pub(crate) fn plaintext_fitness(text: &str) -> f64 {
+    -chi_squared_test(text, 0, &ENGLISH_FREQUENCIES)
+}
+
 // Finds the most likely key given the text and key length.
-fn guess_key(text: &str, key_length: usize) -> String {
-    log_debug(format!("Computed values during key length finding:"));
+pub(crate) fn guess_key(text: &str, key_length: usize) -> String {
+    guess_key_within(text, key_length, 26)
+}
+
+// Finds the most likely key whose shifts all fall below `max_shifts`, such as the digit
+// shifts 0-9 of a Gronsfeld key.
+pub(crate) fn guess_key_within(text: &str, key_length: usize, max_shifts: usize) -> String {
+    debug!("Computed values during key length finding:");
+
+    column_shifts_within(text, key_length, 1, max_shifts)
+        .iter()
+        .enumerate()
+        .map(|(i, shifts)| {
+            let (best_shift, best_chi_squared) = shifts[0];
+            debug!(
+                "Column {}: Best shift: {}, Chi-squared: {:.4}",
+                i, best_shift, best_chi_squared
+            );
+
+            (b'A' + best_shift as u8) as char
+        })
+        .collect()
+}
+
+// Ranks the shifts of every column by chi-squared, keeping the best `top` of each
+// from lowest to highest chi-squared.
+pub(crate) fn column_shifts(text: &str, key_length: usize, top: usize) -> Vec<Vec<(usize, f64)>> {
+    column_shifts_within(text, key_length, top, 26)
+}
 
+// Ranks only the first `max_shifts` shifts of every column, skipping the chi-squared of the rest.
+fn column_shifts_within(
+    text: &str,
+    key_length: usize,
+    top: usize,
+    max_shifts: usize,
+) -> Vec<Vec<(usize, f64)>> {
     (0..key_length)
         .map(|i| {
             let column_text: String = text
@@ -87,70 +264,34 @@ fn guess_key(text: &str, key_length: usize) -> String {
                 })
                 .collect();
 
-            let (best_shift, best_chi_squared) = (0..26)
+            (0..max_shifts.clamp(1, 26))
                 .map(|shift| {
                     let chi_squared = chi_squared_test(&column_text, shift, &ENGLISH_FREQUENCIES);
                     (shift, chi_squared)
                 })
-                .min_by(|(_, chi1), (_, chi2)| chi1.partial_cmp(chi2).unwrap())
-                .unwrap();
-
-            log_debug(format!(
-                "Column {}: Best shift: {}, Chi-squared: {:.4}",
-                i, best_shift, best_chi_squared
-            ));
-
-            (b'A' + best_shift as u8) as char
+                .sorted_by(|(_, chi1), (_, chi2)| chi1.total_cmp(chi2))
+                .take(top.max(1))
+                .collect()
         })
         .collect()
 }
 
-// Decrypts Vigenère ciphertext with a given key length and optional key,
-// returning the key, decrypted text, and confidence.
-pub fn decrypt_vigenere(
-    ciphertext: &str,
-    key_length: usize,
-    key_option: Option<String>,
-) -> (String, String, f64) {
-    if let Some(key) = key_option {
-        let decrypted_text = vigenere_decrypt(ciphertext, &key);
-        let mic = mutual_index_of_coincidence(&decrypted_text, ENGLISH_FREQUENCIES);
-        let confidence = confidence_level(mic, 0.066); // The average MIC for English text is 0.066
-        return (key.clone(), decrypted_text, confidence);
-    }
-
-    let key = guess_key(ciphertext, key_length);
-    let decrypted_text = vigenere_decrypt(ciphertext, &key);
-    let mic = mutual_index_of_coincidence(&decrypted_text, ENGLISH_FREQUENCIES);
-    let confidence = confidence_level(mic, 0.066);
-
-    (key, decrypted_text, confidence)
-}
-
-// Calculates the mutual index of coincidence (MIC) between the text and English frequencies.
-fn mutual_index_of_coincidence(text: &str, english_freq: [(char, f64); 26]) -> f64 {
-    let freq_map = character_frequency_f64(text);
-    let total_chars = text.chars().count() as f64;
-
-    let mut mic = 0.0;
-
-    for (ch, e_freq) in english_freq.iter() {
-        if let Some(freq) = freq_map.get(ch) {
-            mic += (freq / total_chars) * (e_freq / 26.0);
-        }
-    }
-    log_debug(format!("MIC: {}", mic));
-
-    mic
-}
-
-// Calculates the confidence level of the decryption based on MIC and average English MIC.
-fn confidence_level(mic: f64, english_mic: f64) -> f64 {
-    (mic / english_mic) * 100.0
+// Chi-squared of every shift of every column for a key length, indexed by column and shift.
+pub(crate) fn column_chi_squared(text: &str, key_length: usize) -> Vec<[f64; 26]> {
+    column_shifts(text, key_length, 26)
+        .into_iter()
+        .map(|shifts| {
+            let mut column = [0.0; 26];
+            for (shift, chi_squared) in shifts {
+                column[shift] = chi_squared;
+            }
+            column
+        })
+        .collect()
 }
 
 // Computes the frequency of characters in the given text as a HashMap with f64 values.
-pub fn character_frequency_f64(text: &str) -> HashMap<char, f64> {
+pub(crate) fn character_frequency_f64(text: &str) -> HashMap<char, f64> {
     let mut frequency_map = HashMap::new();
     let mut char_count = 0;
 
@@ -168,3 +309,78 @@ pub fn character_frequency_f64(text: &str) -> HashMap<char, f64> {
 
     frequency_map
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::cipher_variant::CipherVariant;
+
+    // Mixed case, punctuation, digits and a non-ASCII letter, to exercise every policy.
+    pub(crate) const PLAINTEXT: &str = "Attack at dawn! Meet me by the old oak, Café 42.";
+
+    // Checks that decrypting the encryption of `PLAINTEXT` gives it back under every key
+    // advancement policy, as its letters alone when they are stripped.
+    pub(crate) fn assert_round_trips(
+        label: &str,
+        encrypt: impl Fn(&str, KeyAdvance) -> Result<String>,
+        decrypt: impl Fn(&str, KeyAdvance) -> Result<String>,
+    ) {
+        for advance in [
+            KeyAdvance::LettersOnly,
+            KeyAdvance::AllCharacters,
+            KeyAdvance::Strip,
+        ] {
+            let ciphertext = encrypt(PLAINTEXT, advance).unwrap();
+            assert_ne!(ciphertext, PLAINTEXT, "{} {:?}", label, advance);
+            let expected = match advance {
+                KeyAdvance::Strip => letters(PLAINTEXT),
+                _ => PLAINTEXT.to_string(),
+            };
+            assert_eq!(
+                decrypt(&ciphertext, advance).unwrap(),
+                expected,
+                "{} {:?}",
+                label,
+                advance
+            );
+        }
+    }
+
+    #[test]
+    fn vigenere_encrypts_the_textbook_example() {
+        assert_eq!(
+            vigenere_encrypt("ATTACKATDAWN", "LEMON").unwrap(),
+            "LXFOPVEFRNHR"
+        );
+        assert_eq!(
+            vigenere_decrypt("LXFOPVEFRNHR", "LEMON").unwrap(),
+            "ATTACKATDAWN"
+        );
+    }
+
+    #[test]
+    fn decrypt_inverts_encrypt_for_every_policy_and_variant() {
+        for variant in CipherVariant::ALL {
+            let key = match variant {
+                CipherVariant::Gronsfeld => "31415",
+                _ => "LEMON",
+            };
+            assert_round_trips(
+                &variant.to_string(),
+                |text, advance| variant.encrypt(text, key, advance),
+                |text, advance| variant.decrypt(text, key, advance),
+            );
+        }
+    }
+
+    #[test]
+    fn key_advance_decides_which_characters_consume_key_letters() {
+        // The space takes the key's B under `all`, so the second A meets the third key letter
+        assert_eq!(KeyAdvance::LettersOnly.encrypt("A A", "AB").unwrap(), "A B");
+        assert_eq!(
+            KeyAdvance::AllCharacters.encrypt("A A", "AB").unwrap(),
+            "A A"
+        );
+        assert_eq!(KeyAdvance::Strip.encrypt("A A", "AB").unwrap(), "AB");
+    }
+}
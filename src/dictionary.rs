@@ -0,0 +1,276 @@
+// src/dictionary.rs
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use itertools::Itertools;
+use log::debug;
+use rayon::prelude::*;
+
+use crate::decryption::{column_chi_squared, vigenere_decrypt};
+use crate::error::Result;
+use crate::fitness::Fitness;
+
+// Keys kept after ranking by column chi-squared, before scoring whole plaintexts.
+pub(crate) const SCREENED_KEYS: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DictionaryConfig {
+    // Candidate keys, uppercase and unique.
+    pub words: Vec<String>,
+    // Also tries every pair of words joined together as the key.
+    pub pairs: bool,
+    // Best dictionary keys reported.
+    pub hits: usize,
+}
+
+impl DictionaryConfig {
+    pub fn new(words: Vec<String>) -> Self {
+        DictionaryConfig {
+            words,
+            pairs: false,
+            hits: 3,
+        }
+    }
+}
+
+// Reads a wordlist with one word per line or whitespace separated, keeping only the letters
+// of each word in uppercase and dropping duplicates.
+pub fn parse_wordlist(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_ascii_alphabetic())
+                .collect::<String>()
+                .to_ascii_uppercase()
+        })
+        .filter(|word| !word.is_empty())
+        .unique()
+        .collect()
+}
+
+// Tries every dictionary word, and word pair when enabled, as the key and returns the best
+// hits with their plaintext fitness from best to worst. Only keys whose length divides one
+// of `key_lengths` are tried when given.
+pub(crate) fn dictionary_attack<'a>(
+    ciphertext: &str,
+    key_lengths: Option<&[usize]>,
+    fitness: Fitness,
+    config: &'a DictionaryConfig,
+) -> Result<Vec<(String, f64)>> {
+    let fits = |length: usize| {
+        key_lengths.is_none_or(|key_lengths| {
+            key_lengths
+                .iter()
+                .any(|&key_length| key_length % length == 0)
+        })
+    };
+
+    // Words grouped by length, in order, so pairs can be screened a pair of lengths at a time
+    let mut by_length: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for word in &config.words {
+        by_length.entry(word.len()).or_default().push(word);
+    }
+
+    // Cheaply rank every key by how well its shifts fit each column's letter frequencies,
+    // keeping only the best in a bounded heap so the keys are never all built
+    let mut tables: HashMap<usize, Vec<[f64; 26]>> = HashMap::new();
+    let mut heap: BinaryHeap<Screened> = BinaryHeap::new();
+    let mut candidates = 0;
+    for (&length, words) in by_length.iter().filter(|&(&length, _)| fits(length)) {
+        let table = tables
+            .entry(length)
+            .or_insert_with(|| column_chi_squared(ciphertext, length));
+        for word in words {
+            push_screened(
+                &mut heap,
+                Screened {
+                    chi_squared: word_chi_squared(word, table, 0),
+                    first: word,
+                    second: "",
+                },
+            );
+        }
+        candidates += words.len();
+    }
+    if config.pairs {
+        for ((&first_length, firsts), (&second_length, seconds)) in
+            by_length.iter().cartesian_product(&by_length)
+        {
+            let length = first_length + second_length;
+            if !fits(length) {
+                continue;
+            }
+            candidates += firsts.len() * seconds.len();
+            let table = tables
+                .entry(length)
+                .or_insert_with(|| column_chi_squared(ciphertext, length));
+
+            // A pair's chi-squared is the sum of its words' over their own columns, so with both
+            // halves sorted the search stops once no remaining pair can enter the heap
+            let halves = |words: &[&'a str], offset: usize| -> Vec<(f64, &'a str)> {
+                words
+                    .iter()
+                    .map(|&word| (word_chi_squared(word, table, offset), word))
+                    .sorted_by(|a, b| a.0.total_cmp(&b.0))
+                    .collect()
+            };
+            let firsts = halves(firsts, 0);
+            let seconds = halves(seconds, first_length);
+            for &(first_chi_squared, first) in &firsts {
+                if first_chi_squared + seconds[0].0 >= screened_bound(&heap) {
+                    break;
+                }
+                for &(second_chi_squared, second) in &seconds {
+                    let chi_squared = first_chi_squared + second_chi_squared;
+                    if chi_squared >= screened_bound(&heap) {
+                        break;
+                    }
+                    push_screened(
+                        &mut heap,
+                        Screened {
+                            chi_squared,
+                            first,
+                            second,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    debug!("Dictionary attack: {} candidate keys", candidates);
+
+    // Only the survivors are built into keys
+    let screened: Vec<((), String)> = heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|screened| ((), format!("{}{}", screened.first, screened.second)))
+        .collect();
+
+    let hits = rank_survivors(ciphertext, fitness, screened, config.hits)?;
+    Ok(hits
+        .into_iter()
+        .map(|((), key, score)| (key, score))
+        .collect())
+}
+
+// Ranks screened keys, each carried with its caller's tag, by the fitness of their whole
+// plaintext and returns the best `hits` from best to worst.
+pub(crate) fn rank_survivors<T: Send>(
+    ciphertext: &str,
+    fitness: Fitness,
+    screened: Vec<(T, String)>,
+    hits: usize,
+) -> Result<Vec<(T, String, f64)>> {
+    let mut ranked = screened
+        .into_par_iter()
+        .map(|(tag, key)| {
+            let plaintext = vigenere_decrypt(ciphertext, &key)?;
+            let score = fitness.score_decryption(&plaintext, key.len());
+            Ok((tag, key, score))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+    ranked.truncate(hits.max(1));
+    Ok(ranked)
+}
+
+// A screened key, one word or a pair, ordered by its column chi-squared in the bounded heap.
+#[derive(Debug)]
+struct Screened<'a> {
+    chi_squared: f64,
+    first: &'a str,
+    second: &'a str,
+}
+
+impl PartialEq for Screened<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Screened<'_> {}
+
+impl PartialOrd for Screened<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Screened<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.chi_squared.total_cmp(&other.chi_squared)
+    }
+}
+
+// Chi-squared of a word's shifts against the columns of a table starting at `offset`.
+fn word_chi_squared(word: &str, table: &[[f64; 26]], offset: usize) -> f64 {
+    word.bytes()
+        .zip(&table[offset..])
+        .map(|(k, column)| column[(k - b'A') as usize])
+        .sum()
+}
+
+// Chi-squared a key must beat to enter the heap, infinite until the heap is full.
+fn screened_bound(heap: &BinaryHeap<Screened>) -> f64 {
+    if heap.len() < SCREENED_KEYS {
+        f64::INFINITY
+    } else {
+        heap.peek().map_or(f64::INFINITY, |worst| worst.chi_squared)
+    }
+}
+
+// Adds a key to the heap, evicting the worst once it holds `SCREENED_KEYS`.
+fn push_screened<'a>(heap: &mut BinaryHeap<Screened<'a>>, screened: Screened<'a>) {
+    if screened.chi_squared < screened_bound(heap) {
+        if heap.len() >= SCREENED_KEYS {
+            heap.pop();
+        }
+        heap.push(screened);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    #[test]
+    fn wordlists_keep_unique_uppercase_letters() {
+        assert_eq!(
+            parse_wordlist("lemon\nLemon's cedar  x-ray\n42\n"),
+            ["LEMON", "LEMONS", "CEDAR", "XRAY"]
+        );
+    }
+
+    #[test]
+    fn the_key_word_ranks_first() {
+        let config = DictionaryConfig::new(parse_wordlist("CEDAR LEMON ORANGE MELON APPLE"));
+        let hits = dictionary_attack(&lemon(), None, Fitness::Quadgram, &config).unwrap();
+        assert_eq!(hits[0].0, "LEMON");
+        assert_eq!(hits.len(), config.hits);
+    }
+
+    #[test]
+    fn word_pairs_are_tried_when_enabled() {
+        let mut config = DictionaryConfig::new(parse_wordlist("LEM ON AB"));
+        config.hits = 1;
+        let hits = dictionary_attack(&lemon(), None, Fitness::Quadgram, &config).unwrap();
+        assert_ne!(hits[0].0, "LEMON");
+
+        config.pairs = true;
+        let hits = dictionary_attack(&lemon(), None, Fitness::Quadgram, &config).unwrap();
+        assert_eq!(hits[0].0, "LEMON");
+    }
+
+    #[test]
+    fn only_words_fitting_the_key_lengths_are_tried() {
+        let config = DictionaryConfig::new(parse_wordlist("LEMON ORANGE"));
+        let hits = dictionary_attack(&lemon(), Some(&[6]), Fitness::Quadgram, &config).unwrap();
+        assert!(hits.iter().all(|(key, _)| key == "ORANGE"));
+    }
+}
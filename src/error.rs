@@ -0,0 +1,51 @@
+// src/error.rs
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Errors that can occur while reading or cracking a ciphertext.
+#[derive(Debug)]
+pub enum CrackError {
+    Io(io::Error),
+    EmptyCiphertext,
+    CiphertextTooShort { letters: usize, minimum: usize },
+    NoKeyLengthCandidates,
+    InvalidKey(String),
+    InvalidCrib(String),
+}
+
+pub type Result<T> = std::result::Result<T, CrackError>;
+
+impl fmt::Display for CrackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrackError::Io(e) => write!(f, "I/O error: {}", e),
+            CrackError::EmptyCiphertext => write!(f, "ciphertext contains no letters"),
+            CrackError::CiphertextTooShort { letters, minimum } => write!(
+                f,
+                "ciphertext has {} letters but at least {} are required",
+                letters, minimum
+            ),
+            CrackError::NoKeyLengthCandidates => {
+                write!(f, "no key length candidates could be found")
+            }
+            CrackError::InvalidKey(key) => write!(f, "invalid key {:?}", key),
+            CrackError::InvalidCrib(crib) => write!(f, "invalid crib {:?}", crib),
+        }
+    }
+}
+
+impl Error for CrackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CrackError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CrackError {
+    fn from(e: io::Error) -> Self {
+        CrackError::Io(e)
+    }
+}
@@ -0,0 +1,71 @@
+// src/fitness.rs
+use std::str::FromStr;
+
+use crate::decryption::{plaintext_fitness, vigenere_decrypt};
+use crate::error::Result;
+use crate::ngram::{ENGLISH_BIGRAMS, ENGLISH_QUADGRAMS, ENGLISH_TRIGRAMS};
+
+// Plaintext scoring function used to compare candidate decryptions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fitness {
+    // Chi-squared distance from English letter frequencies.
+    Unigram,
+    Bigram,
+    Trigram,
+    // Quadgram log-likelihood, the most reliable on short texts.
+    #[default]
+    Quadgram,
+}
+
+impl FromStr for Fitness {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unigram" | "chi-squared" => Ok(Fitness::Unigram),
+            "bigram" => Ok(Fitness::Bigram),
+            "trigram" => Ok(Fitness::Trigram),
+            "quadgram" => Ok(Fitness::Quadgram),
+            _ => Err(format!("unknown fitness function {:?}", s)),
+        }
+    }
+}
+
+impl Fitness {
+    // Fitness of the plaintext a Vigenère key of uppercase letters decrypts the ciphertext to.
+    pub(crate) fn score_key(self, ciphertext: &str, key: &[u8]) -> Result<f64> {
+        let key = std::str::from_utf8(key).expect("key is ASCII");
+        Ok(self.score(&vigenere_decrypt(ciphertext, key)?))
+    }
+
+    // Scores how English-like a plaintext is, higher is better.
+    pub fn score(self, plaintext: &str) -> f64 {
+        match self {
+            Fitness::Unigram => plaintext_fitness(plaintext),
+            Fitness::Bigram => ENGLISH_BIGRAMS.score(plaintext),
+            Fitness::Trigram => ENGLISH_TRIGRAMS.score(plaintext),
+            Fitness::Quadgram => ENGLISH_QUADGRAMS.score(plaintext),
+        }
+    }
+
+    // Scores a decryption under a key of the given length, higher is better.
+    // Longer keys can overfit short texts, so each key letter is charged as a free parameter
+    // in the Bayesian information criterion. Chi-squared approximates -2 ln L and the n-gram
+    // scores are log10 L, hence the different scaling.
+    pub fn score_decryption(self, plaintext: &str, key_length: usize) -> f64 {
+        let letters = plaintext
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .count();
+        let letters = letters.max(1) as f64;
+        match self {
+            Fitness::Unigram => self.score(plaintext) - key_length as f64 * letters.ln(),
+            _ => self.score(plaintext) - key_length as f64 * letters.log10() / 2.0,
+        }
+    }
+}
+
+// Confidence that a plaintext is English as a percentage, from its quadgram log-likelihood.
+pub(crate) fn confidence(plaintext: &str) -> f64 {
+    ENGLISH_QUADGRAMS.englishness(plaintext) * 100.0
+}
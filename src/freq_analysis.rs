@@ -1,7 +1,8 @@
 // src/freq_analysis.rs
-use crate::logger::log_timing;
+use crate::error::{CrackError, Result};
 use aho_corasick::AhoCorasickBuilder;
 use lazy_static::lazy_static;
+use log::info;
 use std::collections::HashMap;
 use std::time::Instant;
 
@@ -39,8 +40,11 @@ lazy_static! {
     .collect();
 }
 
+// Fewest letters a ciphertext needs for the statistics to be meaningful.
+pub(crate) const MIN_CIPHERTEXT_LETTERS: usize = 8;
+
 // Computes the frequency of ASCII alphabetic characters in the given text.
-pub fn character_frequency_usize(text: &str) -> Vec<usize> {
+pub(crate) fn character_frequency_usize(text: &str) -> Vec<usize> {
     // Initialize a frequency map with 26 entries for each English letter.
     let mut frequency_map = vec![0; 26];
 
@@ -59,7 +63,7 @@ pub fn character_frequency_usize(text: &str) -> Vec<usize> {
 }
 
 // Computes the Index of Coincidence (IC) for the given text.
-pub fn index_of_coincidence(text: &str) -> f64 {
+pub(crate) fn index_of_coincidence(text: &str) -> f64 {
     // Obtain the frequency map of the characters in the text.
     let frequency_map = character_frequency_usize(text);
     // Count the number of ASCII alphabetic characters in the text.
@@ -76,12 +80,12 @@ pub fn index_of_coincidence(text: &str) -> f64 {
 }
 
 // Finds the divisors of the given number.
-fn find_divisors(number: usize) -> Vec<usize> {
+pub(crate) fn find_divisors(number: usize) -> Vec<usize> {
     let mut divisors = Vec::new();
     // Iterate from 1 to the square root of the number.
     for i in 1..=((number as f64).sqrt() as usize) {
         // If the number is divisible by i,
-        if number % i == 0 {
+        if number.is_multiple_of(i) {
             // Add i to the list of divisors.
             divisors.push(i);
             // If i is not the square root of the number, add the other divisor.
@@ -99,8 +103,8 @@ fn find_divisors(number: usize) -> Vec<usize> {
 fn kasiski_examination(text: &str) -> Vec<usize> {
     // Initialize a hashmap to store distances between repeated sequences.
     let mut distances: HashMap<usize, usize> = HashMap::new();
-    // Count the characters in the text.
-    let text_len = text.chars().count();
+    // Count the bytes in the text.
+    let text_len = text.len();
 
     // Set the starting sequence length based on the text length.
     let mut start = 3;
@@ -111,7 +115,11 @@ fn kasiski_examination(text: &str) -> Vec<usize> {
     // Iterate through sequence lengths from the starting length to 4.
     for seq_len in start..=4 {
         // Iterate through the starting positions of the sequences.
-        for start in 0..(text_len - seq_len) {
+        for start in 0..text_len.saturating_sub(seq_len) {
+            // Skip sequences that would split a multi-byte character.
+            if !text.is_char_boundary(start) || !text.is_char_boundary(start + seq_len) {
+                continue;
+            }
             // Extract the sequence from the text.
             let sequence: &str = &text[start..start + seq_len];
             // Create the Aho-Corasick automaton for the sequence.
@@ -145,9 +153,10 @@ fn kasiski_examination(text: &str) -> Vec<usize> {
 
     // Initialize a vector to store the possible key lengths.
     let mut possible_key_lengths: Vec<usize> = Vec::new();
-    // Collect the distances from the hashmap and sort them by their counts in descending order.
+    // Collect the distances from the hashmap and sort them by their counts in descending order,
+    // shorter distances first on ties so the order does not depend on the hashmap's.
     let mut sorted_distances: Vec<(&usize, &usize)> = distances.iter().collect();
-    sorted_distances.sort_by(|a, b| b.1.cmp(a.1));
+    sorted_distances.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
 
     // Iterate through the sorted distances.
     for &(dist, _) in &sorted_distances {
@@ -167,7 +176,20 @@ fn kasiski_examination(text: &str) -> Vec<usize> {
     possible_key_lengths
 }
 
-pub fn analyze_text(text: &str) -> (f64, Vec<usize>) {
+// Computes the IC and the Kasiski key length candidates of a ciphertext.
+pub(crate) fn analyze_text(text: &str) -> Result<(f64, Vec<usize>)> {
+    // Reject texts too short for the IC and Kasiski examination.
+    let letters = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    if letters == 0 {
+        return Err(CrackError::EmptyCiphertext);
+    }
+    if letters < MIN_CIPHERTEXT_LETTERS {
+        return Err(CrackError::CiphertextTooShort {
+            letters,
+            minimum: MIN_CIPHERTEXT_LETTERS,
+        });
+    }
+
     let start = Instant::now();
     let ic = index_of_coincidence(text);
     let ic_dur = start.elapsed();
@@ -175,16 +197,16 @@ pub fn analyze_text(text: &str) -> (f64, Vec<usize>) {
     let possible_key_lengths = kasiski_examination(text);
     let kasiski_dur = start.elapsed();
 
-    log_timing(format!(
+    info!(
         "IC time: {} seconds and {} milliseconds",
         ic_dur.as_secs(),
         ic_dur.subsec_millis()
-    ));
-    log_timing(format!(
+    );
+    info!(
         "Kasiski decryption time: {} seconds and {} milliseconds",
         kasiski_dur.as_secs(),
         kasiski_dur.subsec_millis()
-    ));
+    );
 
-    (ic, possible_key_lengths)
+    Ok((ic, possible_key_lengths))
 }
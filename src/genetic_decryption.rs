@@ -1,96 +1,185 @@
 // src/genetic_decryption.rs
+use std::cmp::Ordering;
 
-fn generate_random_key(key_length: usize) -> String {
-    let mut rng = rand::thread_rng();
-    (0..key_length)
-        .map(|_| (b'A' + rng.gen_range(0..26)) as char)
-        .collect()
+use log::debug;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::decryption::validate_key;
+use crate::error::Result;
+use crate::fitness::Fitness;
+use crate::key_locks::KeyLocks;
+use crate::keygen::{generate_random_key, seeded_rng};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneticConfig {
+    pub population_size: usize,
+    pub generations: usize,
+    // Chance that two parents are recombined rather than copied.
+    pub crossover_rate: f64,
+    // Chance that each letter of an offspring is replaced by a random letter.
+    pub mutation_rate: f64,
+    // Fittest keys copied unchanged into the next generation.
+    pub elitism: usize,
+    // Keys competing in each tournament when picking a parent.
+    pub tournament_size: usize,
+    // Seeds the random number generator for reproducible runs, random when unset.
+    pub seed: Option<u64>,
 }
 
-fn evaluate_key_fitness(decrypted_text: &str) -> f64 {
-    let freq_map = character_frequency(&decrypted_text);
-    ENGLISH_FREQUENCIES
-        .iter()
-        .map(|(c, expected_freq)| {
-            let actual_freq = freq_map.get(c).unwrap_or(&0.0);
-            (actual_freq - expected_freq).abs()
-        })
-        .sum::<f64>()
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        GeneticConfig {
+            population_size: 200,
+            generations: 100,
+            crossover_rate: 0.8,
+            mutation_rate: 0.05,
+            elitism: 4,
+            tournament_size: 5,
+            seed: None,
+        }
+    }
 }
 
-fn genetic_guess_key(text: &str, key_length: usize) -> String {
-    let population_size = 10000;
-    let generations = 10000;
-    let crossover_rate = 0.8;
-    let mutation_rate = 0.15;
-    let tournament_size = 100;
+// Scores every key of the population in parallel, fittest first.
+fn rank_population(
+    text: &str,
+    population: Vec<Vec<u8>>,
+    fitness: Fitness,
+) -> Result<Vec<(Vec<u8>, f64)>> {
+    let mut ranked = population
+        .into_par_iter()
+        .map(|key| {
+            let score = fitness.score_key(text, &key)?;
+            Ok((key, score))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    Ok(ranked)
+}
 
-    let mut rng = rand::thread_rng();
+// Picks the fittest of a few random keys.
+fn tournament<'a>(rng: &mut StdRng, ranked: &'a [(Vec<u8>, f64)], size: usize) -> &'a [u8] {
+    // The population is sorted, so the lowest index drawn is the fittest
+    let winner = (0..size.max(1))
+        .map(|_| rng.gen_range(0..ranked.len()))
+        .min()
+        .unwrap();
+    &ranked[winner].0
+}
 
-    let mut population: Vec<String> = (0..population_size)
-        .map(|_| generate_random_key(key_length))
-        .collect();
+// Evolves a population of keys of the seed key's length towards the best plaintext fitness,
+// returning the fittest key found. Locked positions keep their letters.
+pub(crate) fn genetic_guess_key(
+    text: &str,
+    seed_key: &str,
+    fitness: Fitness,
+    locks: &KeyLocks,
+    config: &GeneticConfig,
+) -> Result<String> {
+    validate_key(seed_key)?;
+    let key_length = seed_key.len();
+    let population_size = config.population_size.max(2);
 
-    for i in 0..generations {
-        // Evaluate the fitness of each key
-        println!("{}%", (i as f64 / generations as f64) * 100 as f64);
-        let mut fitnesses: Vec<(String, f64)> = population
-            .iter()
-            .map(|key| {
-                (
-                    key.clone(),
-                    evaluate_key_fitness(&vigenere_decrypt(text, key)),
-                )
-            })
-            .collect();
+    let mut rng = seeded_rng(config.seed);
 
-        // Sort the population by fitness (ascending)
-        fitnesses.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    // Start from the seed key and fill the rest of the population at random
+    let mut population: Vec<Vec<u8>> = vec![seed_key.to_ascii_uppercase().into_bytes()];
+    population.extend(
+        (1..population_size).map(|_| generate_random_key(&mut rng, key_length).into_bytes()),
+    );
+    for key in population.iter_mut() {
+        locks.apply(key);
+    }
+    let mut ranked = rank_population(text, population, fitness)?;
 
-        // Select the best performing keys for reproduction
-        let mut new_population: Vec<String> = fitnesses
+    for generation in 0..config.generations {
+        // Carry the fittest keys over unchanged
+        let mut new_population: Vec<Vec<u8>> = ranked
             .iter()
-            .take(tournament_size)
+            .take(config.elitism.min(population_size))
             .map(|(key, _)| key.clone())
             .collect();
 
         // Generate offspring
         while new_population.len() < population_size {
-            let parents: Vec<&String> = population.choose_multiple(&mut rng, 2).collect();
+            let mut parents = [
+                tournament(&mut rng, &ranked, config.tournament_size).to_vec(),
+                tournament(&mut rng, &ranked, config.tournament_size).to_vec(),
+            ];
 
-            let mut offspring = if rng.gen::<f64>() < crossover_rate {
-                // Apply crossover
+            // Apply single-point crossover
+            if key_length > 1 && rng.gen::<f64>() < config.crossover_rate {
                 let crossover_point = rng.gen_range(1..key_length);
-                let child1 = format!(
-                    "{}{}",
-                    &parents[0][..crossover_point],
-                    &parents[1][crossover_point..]
-                );
-                let child2 = format!(
-                    "{}{}",
-                    &parents[1][..crossover_point],
-                    &parents[0][crossover_point..]
-                );
-                vec![child1, child2]
-            } else {
-                parents.into_iter().cloned().collect()
-            };
+                let [first, second] = &mut parents;
+                first[crossover_point..].swap_with_slice(&mut second[crossover_point..]);
+            }
 
             // Apply mutation
-            for child in offspring.iter_mut() {
-                for i in 0..key_length {
-                    if rng.gen::<f64>() < mutation_rate {
-                        let new_char = (b'A' + rng.gen_range(0..26)) as char;
-                        child.replace_range(i..i + 1, &new_char.to_string());
+            for child in parents.iter_mut() {
+                for (position, letter) in child.iter_mut().enumerate() {
+                    if !locks.is_locked(position) && rng.gen::<f64>() < config.mutation_rate {
+                        *letter = b'A' + rng.gen_range(0..26);
                     }
                 }
             }
 
-            new_population.append(&mut offspring);
+            new_population.extend(parents);
         }
+        new_population.truncate(population_size);
 
-        population = new_population;
+        ranked = rank_population(text, new_population, fitness)?;
+        debug!(
+            "Generation {}: best key {} ({:.4})",
+            generation,
+            String::from_utf8_lossy(&ranked[0].0),
+            ranked[0].1
+        );
     }
 
-    population[0].clone()
+    Ok(String::from_utf8(ranked.swap_remove(0).0).expect("key is ASCII"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    fn config(seed: u64) -> GeneticConfig {
+        GeneticConfig {
+            population_size: 100,
+            generations: 40,
+            seed: Some(seed),
+            ..GeneticConfig::default()
+        }
+    }
+
+    #[test]
+    fn evolution_finds_the_key_from_a_wrong_seed_key() {
+        let key = genetic_guess_key(
+            &lemon(),
+            "AAAAA",
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            &config(3),
+        )
+        .unwrap();
+        assert_eq!(key, "LEMON");
+    }
+
+    #[test]
+    fn evolution_is_reproducible_and_keeps_locks() {
+        let mut locks = KeyLocks::new();
+        locks.lock(4, 'Z').unwrap();
+        let evolve = || genetic_guess_key(&lemon(), "AAAAA", Fitness::Quadgram, &locks, &config(5));
+        let key = evolve().unwrap();
+        assert!(key.ends_with('Z'), "{}", key);
+        assert_eq!(evolve().unwrap(), key);
+    }
 }
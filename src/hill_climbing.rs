@@ -0,0 +1,125 @@
+// src/hill_climbing.rs
+use log::debug;
+
+use crate::decryption::validate_key;
+use crate::error::Result;
+use crate::fitness::Fitness;
+use crate::key_locks::KeyLocks;
+
+// Greedily changes one key letter at a time, keeping every change that improves the
+// fitness of the plaintext `decrypt` gives, until no single-letter change helps. Each
+// position tries the letters of `alphabet`, such as A-J for the digit shifts of a Gronsfeld
+// key. Locked positions keep their letters.
+pub(crate) fn hill_climb(
+    ciphertext: &str,
+    key: &str,
+    fitness: Fitness,
+    locks: &KeyLocks,
+    alphabet: &[u8],
+    decrypt: impl Fn(&str, &str) -> Result<String>,
+) -> Result<String> {
+    validate_key(key)?;
+
+    let score = |key: &[u8]| -> Result<f64> {
+        let key = std::str::from_utf8(key).expect("key is ASCII");
+        Ok(fitness.score(&decrypt(ciphertext, key)?))
+    };
+    let mut key = locks.applied(key).into_bytes();
+    let mut best_score = score(&key)?;
+
+    loop {
+        let mut improved = false;
+        for position in (0..key.len()).filter(|&position| !locks.is_locked(position)) {
+            let original = key[position];
+            let mut best_letter = original;
+
+            // Try every other letter in this position
+            for &letter in alphabet {
+                if letter == original {
+                    continue;
+                }
+                key[position] = letter;
+                let score = score(&key)?;
+                if score > best_score {
+                    best_score = score;
+                    best_letter = letter;
+                }
+            }
+
+            key[position] = best_letter;
+            if best_letter != original {
+                debug!(
+                    "Hill climbing: position {} {} -> {} ({:.4})",
+                    position, original as char, best_letter as char, best_score
+                );
+                improved = true;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    Ok(String::from_utf8(key).expect("key is ASCII"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::{vigenere_decrypt, KeyAdvance};
+
+    // Uppercase letters of the Vigenère sample whose file name is its key.
+    fn lemon() -> String {
+        KeyAdvance::LettersOnly.analysis_text(include_str!("../input/LEMON.txt"))
+    }
+
+    // Every letter, as tried for a Vigenère key.
+    fn alphabet() -> Vec<u8> {
+        (b'A'..=b'Z').collect()
+    }
+
+    #[test]
+    fn refinement_fixes_a_wrong_column() {
+        let key = hill_climb(
+            &lemon(),
+            "LEMXN",
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            &alphabet(),
+            vigenere_decrypt,
+        )
+        .unwrap();
+        assert_eq!(key, "LEMON");
+    }
+
+    #[test]
+    fn refinement_keeps_locked_letters() {
+        let mut locks = KeyLocks::new();
+        locks.lock(3, 'X').unwrap();
+        let key = hill_climb(
+            &lemon(),
+            "QEMON",
+            Fitness::Quadgram,
+            &locks,
+            &alphabet(),
+            vigenere_decrypt,
+        )
+        .unwrap();
+        assert_eq!(key, "LEMXN");
+    }
+
+    #[test]
+    fn refinement_only_tries_the_given_letters() {
+        let key = hill_climb(
+            &lemon(),
+            "AAAAA",
+            Fitness::Quadgram,
+            &KeyLocks::new(),
+            b"ABCDEFGHIJ",
+            vigenere_decrypt,
+        )
+        .unwrap();
+        assert!(key.bytes().all(|k| k <= b'J'), "{}", key);
+    }
+}
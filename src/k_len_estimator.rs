@@ -1,7 +1,11 @@
 // src/k_len_estimator.rs
-use crate::logger::log_debug;
+use crate::error::{CrackError, Result};
+use crate::freq_analysis::index_of_coincidence;
 use itertools::Itertools;
+use log::debug;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 fn find_gcd_of_list(numbers: Vec<usize>) -> usize {
     fn gcd(a: usize, b: usize) -> usize {
@@ -14,114 +18,257 @@ fn find_gcd_of_list(numbers: Vec<usize>) -> usize {
 
     numbers
         .iter()
-        .filter(|x| x > &&(5 as usize))
+        .filter(|&&x| x > 5)
         .fold(0, |acc, &num| gcd(acc, num))
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyLengthEstimationStrategy {
     Autocorrelation,
-    //IndexOfCoincidence,
-    //FriedmanTest,
-    GCD,
+    IndexOfCoincidence,
+    FriedmanTest,
+    Gcd,
 }
 
-pub fn estimate_key_length_using_multiple_strategies(
+impl FromStr for KeyLengthEstimationStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "autocorrelation" => Ok(KeyLengthEstimationStrategy::Autocorrelation),
+            "ic" | "index-of-coincidence" => Ok(KeyLengthEstimationStrategy::IndexOfCoincidence),
+            "friedman" => Ok(KeyLengthEstimationStrategy::FriedmanTest),
+            "gcd" => Ok(KeyLengthEstimationStrategy::Gcd),
+            _ => Err(format!("unknown key length strategy {:?}", s)),
+        }
+    }
+}
+
+// IC expected of English plaintext
+pub(crate) const ENGLISH_IC: f64 = 0.0667;
+// IC of uniformly random letters
+pub(crate) const RANDOM_IC: f64 = 1.0 / 26.0;
+// Fewest letters per column for a key length to be considered, below which column
+// statistics are noise.
+pub(crate) const MIN_COLUMN_LETTERS: usize = 8;
+// Agreement of a key length that every candidate bears out: half the gap between the IC of
+// English and of random letters, which the scoring strategies all move by.
+const SCORE_TOLERANCE: f64 = (ENGLISH_IC - RANDOM_IC) / 2.0;
+// Score gap within which key lengths are indistinguishable, a sixteenth of full agreement
+const TIE_TOLERANCE: f64 = SCORE_TOLERANCE / 16.0;
+
+// A key length considered during estimation with its score and share of the probability mass.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct KeyLengthCandidate {
+    pub key_length: usize,
+    pub score: f64,
+    pub probability: f64,
+}
+
+// Ranks the possible key lengths from most to least likely, one entry per distinct length.
+pub(crate) fn estimate_key_length_using_multiple_strategies(
     strategies: &[KeyLengthEstimationStrategy],
-    mut possible_key_lengths: Vec<usize>,
+    possible_key_lengths: Vec<usize>,
     text: &str,
     specified_key_length: Option<usize>,
     frequency_multiplier: f64,
-) -> usize {
+) -> Result<Vec<KeyLengthCandidate>> {
     // If a key length is specified, return it directly without any computation
     if let Some(key_length) = specified_key_length {
-        return key_length;
+        return Ok(vec![KeyLengthCandidate {
+            key_length,
+            score: 0.0,
+            probability: 1.0,
+        }]);
+    }
+
+    let candidates =
+        score_key_lengths(strategies, possible_key_lengths, text, frequency_multiplier)?;
+
+    // Sort candidates in descending order of score, shorter lengths first on ties, and keep
+    // the best score of each length
+    let mut ranked: Vec<(usize, f64)> = candidates
+        .into_iter()
+        .sorted_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)))
+        .unique_by(|&(key_length, _)| key_length)
+        .collect();
+
+    // Longer lengths fit noise better, so lengths within tolerance of the best are ordered
+    // shortest first
+    let best_score = ranked[0].1;
+    let tied = ranked
+        .iter()
+        .take_while(|&&(_, score)| score >= best_score - TIE_TOLERANCE)
+        .count();
+    ranked[..tied].sort_by_key(|&(key_length, _)| key_length);
+
+    // Softmax of the scores, scaled so falling a quarter of full agreement behind the best
+    // multiplies the odds by 1/e, which is about the gap between a key length and its double.
+    // At the default multiplier a repeated Kasiski distance is worth 1.25 of those steps.
+    // Tied lengths share the best score's weight.
+    let temperature = SCORE_TOLERANCE / 4.0;
+    let weights: Vec<f64> = ranked
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, score))| {
+            let score = if i < tied { best_score } else { score };
+            ((score - best_score) / temperature).exp()
+        })
+        .collect();
+    let total_weight: f64 = weights.iter().sum();
+
+    let ranked: Vec<KeyLengthCandidate> = ranked
+        .into_iter()
+        .zip(weights)
+        .map(|((key_length, score), weight)| KeyLengthCandidate {
+            key_length,
+            score,
+            probability: weight / total_weight,
+        })
+        .collect();
+
+    // Print the top 5 candidates
+    debug!("Top Candidates: ");
+    for candidate in ranked.iter().take(5) {
+        debug!(
+            "({}, {}, {:.4}), ",
+            candidate.key_length, candidate.score, candidate.probability
+        );
     }
 
-    //let friedman_lens: &mut Vec<usize> = &mut Vec::new();
+    Ok(ranked)
+}
+
+// Scores every possible key length, in the order they were considered
+fn score_key_lengths(
+    strategies: &[KeyLengthEstimationStrategy],
+    mut possible_key_lengths: Vec<usize>,
+    text: &str,
+    frequency_multiplier: f64,
+) -> Result<Vec<(usize, f64)>> {
+    let letter_count = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+
+    // The Friedman estimate is computed once and also offered as a candidate
+    let friedman = friedman_estimate(text);
+    if strategies.contains(&KeyLengthEstimationStrategy::FriedmanTest)
+        && friedman.is_finite()
+        && friedman >= 1.0
+    {
+        debug!("Friedman estimate: {}", friedman);
+        possible_key_lengths.push(friedman.round() as usize);
+    }
+
+    // Discard lengths that leave too few letters per column
+    possible_key_lengths
+        .retain(|&key_length| key_length > 0 && key_length * MIN_COLUMN_LETTERS <= letter_count);
+    // Without repeats at a usable spacing, every usable length is scored instead
+    if possible_key_lengths.is_empty() {
+        possible_key_lengths.extend(2..=letter_count / MIN_COLUMN_LETTERS);
+    }
+
+    // The GCD strategy only proposes a length, so it takes no part in the average
+    let scoring_strategies = strategies
+        .iter()
+        .filter(|&&strategy| strategy != KeyLengthEstimationStrategy::Gcd)
+        .count();
+
     let mut len = 0;
-    let mut candidates: Vec<(usize, f64)> = Vec::new();
+    let mut scored: Vec<(usize, f64, f64)> = Vec::new();
 
     // Score each possible key
     for key_length in possible_key_lengths.clone() {
         let mut sum_scores = 0.0;
 
         for strategy in strategies {
-            let _ = match strategy {
+            match strategy {
                 KeyLengthEstimationStrategy::Autocorrelation => {
-                    let score = autocorrelation_score(key_length, text);
-                    log_debug(format!("Autocorrelation Score: {}", score));
+                    let score = autocorrelation_score(key_length, text)?;
+                    debug!("Autocorrelation Score: {}", score);
                     sum_scores += score;
                 }
-                /*
                 KeyLengthEstimationStrategy::IndexOfCoincidence => {
-                    let score = index_of_coincidence_score(key_length, text);
-                    log_debug(format!("Index of Coincidence Score: {}", score));
-                    sum_scores += score
+                    let score = index_of_coincidence_score(key_length, text)?;
+                    debug!("Index of Coincidence Score: {}", score);
+                    sum_scores += score;
                 }
                 KeyLengthEstimationStrategy::FriedmanTest => {
-                    let (score, len) = friedman_test(key_length, text);
-                    friedman_lens.push(len.clone());
-                    log_debug(format!("Friedman Test Score: {}, Len: {}", score, len));
-                    sum_scores += score * 10.0
-                }*/
-                KeyLengthEstimationStrategy::GCD => {
+                    let score = friedman_score(key_length, friedman);
+                    debug!("Friedman Test Score: {}", score);
+                    sum_scores += score;
+                }
+                KeyLengthEstimationStrategy::Gcd => {
                     len = find_gcd_of_list(possible_key_lengths.clone());
                 }
-            };
+            }
         }
-        //for i in &mut *friedman_lens {
-        //    possible_key_lengths.push(*i);
-        //}
         // Weight towards the result of find_gcd_of_list
-        if strategies.contains(&KeyLengthEstimationStrategy::GCD) && len > 5 {
+        if strategies.contains(&KeyLengthEstimationStrategy::Gcd) && len > 5 {
             possible_key_lengths.push(len);
         }
 
-        let frequency_map = create_frequency_map(&possible_key_lengths.clone());
+        let frequency_map = create_frequency_map(&possible_key_lengths);
 
-        log_debug(format!(
-            "Possible key lengths after GCD: {:?}",
-            possible_key_lengths
-        ));
+        debug!("Possible key lengths after GCD: {:?}", possible_key_lengths);
 
-        // Weight towards lengths that appear multiple times
+        // Weight towards lengths that appear multiple times, each repeat adding
+        // `frequency_multiplier` tie tolerances so the bonus shares the agreement's units
         let mut frequency_bonus = 0.0;
         if key_length > 3 {
-            frequency_bonus =
-                (*frequency_map.get(&key_length).unwrap_or(&0)) as f64 * frequency_multiplier;
+            frequency_bonus = (*frequency_map.get(&key_length).unwrap_or(&0)) as f64
+                * frequency_multiplier
+                * TIE_TOLERANCE;
         }
 
         // Calculate avg score
-        let avg_score = (sum_scores / strategies.len() as f64) + frequency_bonus;
+        let avg_score = sum_scores / scoring_strategies.max(1) as f64;
 
-        log_debug(format!(
-            "Key length: {}, Average Score: {}",
-            key_length, avg_score
-        ));
-        candidates.push((key_length, avg_score));
+        debug!(
+            "Key length: {}, Average Score: {}, Frequency Bonus: {}",
+            key_length, avg_score, frequency_bonus
+        );
+        scored.push((key_length, avg_score, frequency_bonus));
     }
 
-    // Print the top 5 candidates
-    log_debug(format!("Top Candidates: "));
-    let top_candidates: Vec<_> = candidates
-        .iter()
-        .sorted_by(|a, b| b.1.partial_cmp(&a.1).unwrap()) // Sort candidates in descending order of score
-        .take(5)
-        .collect();
-
-    for &(len, score) in &top_candidates {
-        log_debug(format!("({}, {}), ", len, score));
+    if scored.is_empty() {
+        return Err(CrackError::NoKeyLengthCandidates);
     }
-    candidates
+
+    // Every multiple of the key length is a period of the ciphertext too and scores like
+    // English, while the other lengths score like random letters. A length is scored by how well
+    // that prediction fits the average scores of all candidates, so a multiple loses the
+    // agreement of the true length's odd multiples and one unlucky sample matters less.
+    let midpoint = 1.0 + (ENGLISH_IC + RANDOM_IC) / 2.0;
+    let averages: Vec<(usize, f64)> = scored
         .iter()
-        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-        .unwrap()
-        .0
+        .map(|&(key_length, avg_score, _)| (key_length, avg_score))
+        .unique_by(|&(key_length, _)| key_length)
+        .collect();
+    Ok(scored
+        .into_iter()
+        .map(|(key_length, _, frequency_bonus)| {
+            // GCD alone leaves nothing to agree with
+            let agreement = if scoring_strategies == 0 {
+                0.0
+            } else {
+                averages
+                    .iter()
+                    .map(|&(other, avg_score)| {
+                        if other % key_length == 0 {
+                            avg_score - midpoint
+                        } else {
+                            midpoint - avg_score
+                        }
+                    })
+                    .sum::<f64>()
+                    / averages.len() as f64
+            };
+            (key_length, agreement + frequency_bonus)
+        })
+        .collect())
 }
 
 // Helper for converting from Vec to HashMap
-pub fn create_frequency_map(key_lengths: &Vec<usize>) -> HashMap<usize, usize> {
+fn create_frequency_map(key_lengths: &[usize]) -> HashMap<usize, usize> {
     let mut frequency_map = HashMap::new();
 
     for key_length in key_lengths {
@@ -131,9 +278,16 @@ pub fn create_frequency_map(key_lengths: &Vec<usize>) -> HashMap<usize, usize> {
     frequency_map
 }
 
-pub fn autocorrelation_score(key_length: usize, text: &str) -> f64 {
+pub(crate) fn autocorrelation_score(key_length: usize, text: &str) -> Result<f64> {
     let bytes = text.as_bytes();
     let len = bytes.len();
+    // The text must be longer than the shift to compare any characters
+    if key_length >= len {
+        return Err(CrackError::CiphertextTooShort {
+            letters: len,
+            minimum: key_length + 1,
+        });
+    }
     let mut correlation_count = 0;
 
     for i in 0..len - key_length {
@@ -145,88 +299,161 @@ pub fn autocorrelation_score(key_length: usize, text: &str) -> f64 {
     // Normalize
     let max_possible_matches = len - key_length;
     let normalized_score = (correlation_count as f64) / (max_possible_matches as f64);
-    1.0 + normalized_score // normalize to the range [0.5, 1.5]
+    Ok(1.0 + normalized_score) // normalize to the range [0.5, 1.5]
 }
 
-/*
-pub fn index_of_coincidence_score(key_length: usize, text: &str) -> f64 {
-    let text_length = text.len() as f64;
+// Scores a key length by the IC of its cosets, averaged over every column.
+pub(crate) fn index_of_coincidence_score(key_length: usize, text: &str) -> Result<f64> {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    // Every column needs at least two letters to have an IC
+    if key_length == 0 || letters.len() < key_length * 2 {
+        return Err(CrackError::CiphertextTooShort {
+            letters: letters.len(),
+            minimum: key_length.max(1) * 2,
+        });
+    }
 
-    let mut sum_ioc = 0.0;
-    let mut count = 0;
+    // Columns follow character positions, which line up with the key in the analyzed text.
+    // When every character advances the key a column can hold too few letters for an IC,
+    // so those are left out of the average.
+    let column_ics: Vec<f64> = (0..key_length)
+        .map(|i| text.chars().skip(i).step_by(key_length).collect::<String>())
+        .filter(|column| column.chars().filter(|c| c.is_ascii_alphabetic()).count() >= 2)
+        .map(|column| index_of_coincidence(&column))
+        .collect();
+    let mean_ic = column_ics.iter().sum::<f64>() / column_ics.len().max(1) as f64;
 
-    for i in 0..key_length {
-        let mut freq_map: HashMap<char, usize> = HashMap::new();
+    // Shift into the same range as the autocorrelation score
+    Ok(1.0 + mean_ic)
+}
 
-        for (j, c) in text.chars().enumerate() {
-            if j % key_length == i {
-                *freq_map.entry(c).or_insert(0) += 1;
-            }
-        }
+// Estimates the key length with the Friedman test's closed-form formula.
+pub(crate) fn friedman_estimate(text: &str) -> f64 {
+    let n = text.chars().filter(|c| c.is_ascii_alphabetic()).count() as f64;
+    let ic = index_of_coincidence(text);
 
-        let ioc: f64 = freq_map
-            .values()
-            .map(|&freq| freq as f64 * (freq as f64 - 1.0))
-            .sum::<f64>()
-            / (text_length * (text_length - 1.0));
+    (ENGLISH_IC - RANDOM_IC) * n / ((n - 1.0) * ic - RANDOM_IC * n + ENGLISH_IC)
+}
 
-        sum_ioc += ioc;
-        count += 1;
+// Scores a key length by its distance from the Friedman estimate.
+pub(crate) fn friedman_score(key_length: usize, estimate: f64) -> f64 {
+    // Near-random text yields a negative or unbounded estimate that says nothing
+    if !estimate.is_finite() || estimate <= 0.0 {
+        return 1.0;
     }
-
-    sum_ioc / count as f64
+    1.0 + ENGLISH_IC / (1.0 + (key_length as f64 - estimate).abs())
 }
 
-fn friedman_test(max_key_length: usize, text: &str) -> (f64, usize) {
-    let english_ic = 0.0667;
-
-    let mut best_key_length = 1;
-    let mut smallest_ic_difference = f64::MAX;
-
-    for key_length in 1..=max_key_length {
-        let mut sum_ic = 0.0;
-
-        for i in 0..key_length {
-            let substring: String = text
-                .chars()
-                .enumerate()
-                .filter_map(|(j, c)| if j % key_length == i { Some(c) } else { None })
-                .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::KeyAdvance;
+    use crate::freq_analysis::analyze_text;
+
+    // Vigenère samples with the length of the key each file is named after.
+    const SAMPLES: [(&str, usize); 6] = [
+        (include_str!("../input/LEMON.txt"), 5),
+        (include_str!("../input/CEDAR.txt"), 5),
+        (include_str!("../input/BJIE.txt"), 4),
+        (include_str!("../input/ORANGES.txt"), 7),
+        (include_str!("../input/POTTER.txt"), 6),
+        (include_str!("../input/YUKICHI.txt"), 7),
+    ];
+
+    // Ranked candidates for a ciphertext under the given strategies and default multiplier.
+    fn candidates_with(
+        strategies: &[KeyLengthEstimationStrategy],
+        ciphertext: &str,
+    ) -> Vec<KeyLengthCandidate> {
+        let text = KeyAdvance::LettersOnly.analysis_text(ciphertext);
+        let (_, possible_key_lengths) = analyze_text(&text).unwrap();
+        estimate_key_length_using_multiple_strategies(
+            strategies,
+            possible_key_lengths,
+            &text,
+            None,
+            5.0,
+        )
+        .unwrap()
+    }
 
-            //let (freq_map, _, _) = analyze_text(&substring);
-            let ic = calculate_index_of_coincidence(&substring, substring.len());
+    // Ranked candidates for a ciphertext under the default strategies.
+    fn candidates(ciphertext: &str) -> Vec<KeyLengthCandidate> {
+        candidates_with(
+            &[
+                KeyLengthEstimationStrategy::Autocorrelation,
+                KeyLengthEstimationStrategy::Gcd,
+            ],
+            ciphertext,
+        )
+    }
 
-            sum_ic += ic;
+    #[test]
+    fn every_strategy_ranks_the_key_length_first() {
+        for strategies in [
+            &[KeyLengthEstimationStrategy::Autocorrelation][..],
+            &[KeyLengthEstimationStrategy::IndexOfCoincidence],
+            &[KeyLengthEstimationStrategy::Gcd],
+            &[
+                KeyLengthEstimationStrategy::Autocorrelation,
+                KeyLengthEstimationStrategy::Gcd,
+            ],
+        ] {
+            for (ciphertext, key_length) in SAMPLES {
+                let candidates = candidates_with(strategies, ciphertext);
+                assert_eq!(candidates[0].key_length, key_length, "{:?}", strategies);
+            }
         }
+    }
 
-        let avg_ic = sum_ic / key_length as f64;
-        let ic_difference = (english_ic - avg_ic).abs();
-
-        if ic_difference < smallest_ic_difference {
-            smallest_ic_difference = ic_difference;
-            best_key_length = key_length;
-        }
+    #[test]
+    fn a_specified_key_length_is_the_only_candidate() {
+        let text = KeyAdvance::LettersOnly.analysis_text(SAMPLES[0].0);
+        let candidates = estimate_key_length_using_multiple_strategies(
+            &[KeyLengthEstimationStrategy::Autocorrelation],
+            Vec::new(),
+            &text,
+            Some(9),
+            5.0,
+        )
+        .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].key_length, 9);
     }
 
-    (smallest_ic_difference, best_key_length)
-}
+    #[test]
+    fn friedman_estimate_grows_with_the_key_length() {
+        let text =
+            KeyAdvance::LettersOnly.analysis_text(include_str!("../input/RANDOMNOTRANDOM.txt"));
+        let estimate = friedman_estimate(&text);
+        assert!(estimate > friedman_estimate(&KeyAdvance::LettersOnly.analysis_text(SAMPLES[0].0)));
+    }
 
-fn calculate_index_of_coincidence(text: &str, length: usize) -> f64 {
-    fn create_frequency_map(text: &str) -> HashMap<char, usize> {
-        let mut freq_map = HashMap::new();
+    // Probability given to a key length, zero when it is not a candidate.
+    fn probability(candidates: &[KeyLengthCandidate], key_length: usize) -> f64 {
+        candidates
+            .iter()
+            .find(|candidate| candidate.key_length == key_length)
+            .map_or(0.0, |candidate| candidate.probability)
+    }
 
-        for c in text.chars() {
-            *freq_map.entry(c).or_insert(0) += 1;
+    #[test]
+    fn probabilities_sum_to_one_without_saturating() {
+        for ciphertext in [SAMPLES[0].0, SAMPLES[2].0] {
+            let candidates = candidates(ciphertext);
+            let total: f64 = candidates
+                .iter()
+                .map(|candidate| candidate.probability)
+                .sum();
+            assert!((total - 1.0).abs() < 1e-9);
+            assert!(candidates[0].probability < 0.99);
+            assert!(candidates[1].probability > 0.0);
         }
-
-        freq_map
     }
-    let freq_map = create_frequency_map(text);
 
-    freq_map
-        .values()
-        .map(|&count| count * (count - 1))
-        .sum::<usize>() as f64
-        / (length * (length - 1)) as f64
+    #[test]
+    fn kasiski_repeats_leave_the_true_length_a_share() {
+        let candidates = candidates(include_str!("../input/TYLGH.txt"));
+        assert!(probability(&candidates, 5) > 0.05);
+    }
 }
-*/
@@ -0,0 +1,164 @@
+// src/key_locks.rs
+use crate::error::{CrackError, Result};
+
+// Key letters pinned by the user, which solvers keep while searching the other positions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyLocks {
+    // Uppercase letter locked at each position, None where the solver is free.
+    letters: Vec<Option<u8>>,
+    // Key length implied by a pattern.
+    length: Option<usize>,
+}
+
+impl KeyLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Parses a pattern such as `L?MON`, locking its letters, leaving `?` positions free
+    // and fixing the key length to the pattern's length.
+    pub fn from_pattern(pattern: &str) -> Result<Self> {
+        let letters = pattern
+            .chars()
+            .map(|c| match c {
+                '?' => Ok(None),
+                c if c.is_ascii_alphabetic() => Ok(Some(c.to_ascii_uppercase() as u8)),
+                _ => Err(CrackError::InvalidKey(pattern.to_string())),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if letters.is_empty() {
+            return Err(CrackError::InvalidKey(pattern.to_string()));
+        }
+
+        Ok(KeyLocks {
+            length: Some(letters.len()),
+            letters,
+        })
+    }
+
+    // Locks a letter at a zero-based key position, which must lie within a pattern's length.
+    pub fn lock(&mut self, position: usize, letter: char) -> Result<()> {
+        if !letter.is_ascii_alphabetic() {
+            return Err(CrackError::InvalidKey(letter.to_string()));
+        }
+        if self.length.is_some_and(|length| position >= length) {
+            return Err(CrackError::InvalidKey(format!("{}={}", position, letter)));
+        }
+        if self.letters.len() <= position {
+            self.letters.resize(position + 1, None);
+        }
+        self.letters[position] = Some(letter.to_ascii_uppercase() as u8);
+        Ok(())
+    }
+
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+
+    // Number of key positions up to and including the last locked one.
+    pub fn span(&self) -> usize {
+        self.letters
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |position| position + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.iter().all(Option::is_none)
+    }
+
+    // Letter locked at a position, if any.
+    pub fn letter(&self, position: usize) -> Option<u8> {
+        self.letters.get(position).copied().flatten()
+    }
+
+    pub fn is_locked(&self, position: usize) -> bool {
+        self.letter(position).is_some()
+    }
+
+    // Overwrites the locked positions of an uppercase key, ignoring locks past its end.
+    pub fn apply(&self, key: &mut [u8]) {
+        for (letter, lock) in key.iter_mut().zip(&self.letters) {
+            if let Some(lock) = lock {
+                *letter = *lock;
+            }
+        }
+    }
+
+    // Locks of the inverted key, for cipher variants cracked as Vigenère under it.
+    pub fn inverted(&self) -> Self {
+        KeyLocks {
+            letters: self
+                .letters
+                .iter()
+                .map(|lock| lock.map(|letter| b'A' + (26 - (letter - b'A')) % 26))
+                .collect(),
+            length: self.length,
+        }
+    }
+
+    // Locks for a key `length` long that repeats a key of `period` letters, each lock
+    // repeated at every copy of the shorter key unless a position has a lock of its own.
+    pub(crate) fn repeated(&self, period: usize, length: usize) -> Self {
+        KeyLocks {
+            letters: (0..length)
+                .map(|position| self.letter(position).or(self.letter(position % period)))
+                .collect(),
+            length: self.length,
+        }
+    }
+
+    // Locks of the first `length` positions folded onto a key of `period` letters, for a key
+    // repeating that period which already holds them.
+    pub(crate) fn folded(&self, length: usize, period: usize) -> Self {
+        let mut letters = vec![None; period];
+        for (position, lock) in self.letters.iter().enumerate().take(length) {
+            if let Some(letter) = lock {
+                letters[position % period].get_or_insert(*letter);
+            }
+        }
+        KeyLocks {
+            letters,
+            length: self.length,
+        }
+    }
+
+    // Applies the locks to a key string.
+    pub fn applied(&self, key: &str) -> String {
+        let mut key = key.to_ascii_uppercase().into_bytes();
+        self.apply(&mut key);
+        String::from_utf8(key).expect("key is ASCII")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_locks_letters_and_fixes_the_length() {
+        let locks = KeyLocks::from_pattern("l?mon").unwrap();
+        assert_eq!(locks.length(), Some(5));
+        assert_eq!(locks.applied("XXXXX"), "LXMON");
+        assert!(!locks.is_locked(1));
+        assert!(locks.clone().lock(5, 'A').is_err());
+        assert!(KeyLocks::from_pattern("L-MON").is_err());
+    }
+
+    #[test]
+    fn repeated_locks_cover_every_copy_of_the_key() {
+        let mut locks = KeyLocks::new();
+        locks.lock(0, 'Q').unwrap();
+        locks.lock(7, 'X').unwrap();
+        assert_eq!(locks.repeated(5, 10).applied("ABCDEFGHIJ"), "QBCDEQGXIJ");
+    }
+
+    #[test]
+    fn folded_locks_keep_the_first_key_length() {
+        let mut locks = KeyLocks::new();
+        locks.lock(1, 'E').unwrap();
+        locks.lock(8, 'O').unwrap();
+        locks.lock(12, 'Z').unwrap();
+        assert_eq!(locks.folded(10, 5).applied("AAAAA"), "AEAOA");
+    }
+}
@@ -0,0 +1,91 @@
+// src/keygen.rs
+use std::str::FromStr;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::decryption::ENGLISH_FREQUENCIES;
+use crate::ngram::ENGLISH_BIGRAMS;
+
+// Random number generator seeded for reproducible runs, or from the OS when no seed is given.
+pub fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+// Shape of a generated key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyStyle {
+    // Letters drawn from English bigram statistics, so keys read like made-up words.
+    #[default]
+    WordLike,
+    // Every letter equally likely.
+    Uniform,
+    // Digits equally likely, for Gronsfeld keys.
+    Digits,
+}
+
+impl FromStr for KeyStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "word" | "word-like" => Ok(KeyStyle::WordLike),
+            "uniform" | "random" => Ok(KeyStyle::Uniform),
+            "digits" | "numeric" => Ok(KeyStyle::Digits),
+            _ => Err(format!("unknown key style {:?}", s)),
+        }
+    }
+}
+
+// Generates a key of the given length and style.
+pub fn generate_key<R: Rng>(rng: &mut R, key_length: usize, style: KeyStyle) -> String {
+    match style {
+        KeyStyle::WordLike => generate_word_like_key(rng, key_length),
+        KeyStyle::Uniform => generate_random_key(rng, key_length),
+        KeyStyle::Digits => generate_digit_key(rng, key_length),
+    }
+}
+
+// Draws every digit uniformly at random.
+pub(crate) fn generate_digit_key<R: Rng>(rng: &mut R, key_length: usize) -> String {
+    (0..key_length)
+        .map(|_| (b'0' + rng.gen_range(0..10)) as char)
+        .collect()
+}
+
+// Draws every letter uniformly at random.
+pub(crate) fn generate_random_key<R: Rng>(rng: &mut R, key_length: usize) -> String {
+    (0..key_length)
+        .map(|_| (b'A' + rng.gen_range(0..26)) as char)
+        .collect()
+}
+
+// Draws the first letter by English letter frequency and each following letter by how often
+// it follows the previous one in English.
+pub(crate) fn generate_word_like_key<R: Rng>(rng: &mut R, key_length: usize) -> String {
+    let first = WeightedIndex::new(ENGLISH_FREQUENCIES.iter().map(|&(_, freq)| freq))
+        .expect("frequencies are positive");
+
+    let mut key = String::with_capacity(key_length);
+    let mut previous = None;
+    for _ in 0..key_length {
+        let letter = match previous {
+            None => first.sample(rng) as u8,
+            Some(previous) => {
+                let next = WeightedIndex::new((b'A'..=b'Z').map(|letter| {
+                    10f64.powf(ENGLISH_BIGRAMS.log_probability(&[previous, letter]))
+                }))
+                .expect("probabilities are positive");
+                next.sample(rng) as u8
+            }
+        };
+        let letter = b'A' + letter;
+        key.push(letter as char);
+        previous = Some(letter);
+    }
+    key
+}
@@ -0,0 +1,39 @@
+// src/lib.rs
+#![warn(unreachable_pub)]
+
+mod annealing;
+mod autokey;
+mod beam_search;
+mod cipher_variant;
+mod cracker;
+mod crib;
+mod decryption;
+mod dictionary;
+mod error;
+mod fitness;
+mod freq_analysis;
+mod genetic_decryption;
+mod hill_climbing;
+mod k_len_estimator;
+mod key_locks;
+mod keygen;
+mod ngram;
+mod porta;
+mod quagmire;
+mod solver;
+
+pub use annealing::{AnnealingConfig, TemperatureSchedule};
+pub use autokey::{decrypt as autokey_decrypt, encrypt as autokey_encrypt};
+pub use beam_search::BeamConfig;
+pub use cipher_variant::CipherVariant;
+pub use cracker::{CrackResult, Cracker, CribHit, Decryption, Timings};
+pub use decryption::KeyAdvance;
+pub use dictionary::{parse_wordlist, DictionaryConfig};
+pub use error::{CrackError, Result};
+pub use fitness::Fitness;
+pub use genetic_decryption::GeneticConfig;
+pub use k_len_estimator::{KeyLengthCandidate, KeyLengthEstimationStrategy};
+pub use key_locks::KeyLocks;
+pub use keygen::{generate_key, seeded_rng, KeyStyle};
+pub use quagmire::{QuagmireConfig, QuagmireKey, QuagmireKind};
+pub use solver::KeySolver;
@@ -1,48 +1,81 @@
 // src/main.rs
 
 use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
-use std::time::Duration;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+use clap::Parser;
+use log::{error, info, LevelFilter};
 use requestty::{Answer, Question};
+use vigenere_cracker::{
+    autokey_encrypt, generate_key, parse_wordlist, seeded_rng, AnnealingConfig, BeamConfig,
+    CipherVariant, Cracker, DictionaryConfig, GeneticConfig, KeySolver, KeyStyle, QuagmireConfig,
+    QuagmireKey, QuagmireKind, Result,
+};
 
-mod decryption;
-use decryption::decrypt_vigenere;
-mod freq_analysis;
-use freq_analysis::analyze_text;
-mod k_len_estimator;
-use k_len_estimator::{estimate_key_length_using_multiple_strategies, KeyLengthEstimationStrategy};
-mod logger;
-use logger::{log_debug, log_info, log_timing};
+mod cli;
+use cli::{Cli, Command, CrackArgs, EncryptArgs, KeygenArgs, SolverKind, VariantChoice};
+mod output;
+use output::{write_encryptions, write_keys, write_results, Encryption, OutputFormat};
+
+// Environment variable holding an env_logger filter, used when no -v/-q flag is given
+const LOG_ENV: &str = "VIGENERE_CRACKER_LOG";
+
+// Configure diagnostics on stderr from the verbosity flags or the environment
+fn init_logging(verbose: u8, quiet: bool) {
+    let mut builder = env_logger::Builder::new();
+    builder.format(|buf, record| writeln!(buf, "{}: {}", record.level(), record.args()));
+
+    if quiet {
+        builder.filter_level(LevelFilter::Off);
+    } else if verbose > 0 {
+        builder.filter_level(match verbose {
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        });
+    } else {
+        builder.filter_level(LevelFilter::Warn);
+        builder.parse_env(env_logger::Env::new().filter(LOG_ENV));
+    }
+
+    builder.init();
+}
 
 // Read text from file
-fn read_ciphertext(file_path: &Path) -> Result<String, io::Error> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut ciphertext = String::new();
-    for line in reader.lines() {
-        ciphertext.push_str(&line?);
-    }
-    Ok(ciphertext)
+fn read_ciphertext(file_path: &Path) -> Result<String> {
+    read_text(File::open(file_path)?)
 }
 
-// Main
-fn main() {
-    let input_path = Path::new("./input");
+// Read text from standard input
+fn read_stdin() -> Result<String> {
+    read_text(io::stdin().lock())
+}
+
+// Read all of a reader's text, keeping its line breaks
+fn read_text(reader: impl Read) -> Result<String> {
+    Ok(io::read_to_string(reader)?)
+}
+
+// List the non-hidden file names found directly inside a directory
+fn list_files(dir: &Path) -> Result<Vec<String>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.path().is_file() && !name.starts_with('.') {
+            entries.push(name);
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+// Prompt for files inside the input directory
+fn select_files(input_path: &Path) -> Result<Vec<PathBuf>> {
     // Collect files at path
-    let input_entries: Vec<_> = fs::read_dir(&input_path)
-        .unwrap()
-        .filter_map(|entry| {
-            let entry = entry.unwrap();
-            if entry.path().is_file() {
-                Some(entry.file_name().into_string().unwrap())
-            } else {
-                None
-            }
-        })
-        .collect();
+    let input_entries = list_files(input_path)?;
 
     // Selection prompt
     let question = Question::multi_select("selected_files")
@@ -50,125 +83,315 @@ fn main() {
         .choices(input_entries.iter())
         .build();
 
-    let answer = &requestty::prompt_one(question).unwrap();
-    let selected_files = match answer {
-        Answer::ListItems(items) => items
-            .into_iter()
-            .map(|item| item.clone().text)
-            .collect::<Vec<_>>(),
-        _ => {
-            eprintln!("Error selecting files");
-            std::process::exit(1);
+    let answer = requestty::prompt_one(question).map_err(|e| io::Error::other(e.to_string()))?;
+    match answer {
+        Answer::ListItems(items) => Ok(items
+            .iter()
+            .map(|item| input_path.join(&item.text))
+            .collect()),
+        _ => Err(io::Error::other("unexpected answer to file selection").into()),
+    }
+}
+
+// Resolve the ciphertexts named by the crack subcommand
+fn collect_inputs(args: &CrackArgs) -> Result<Vec<(String, Result<String>)>> {
+    if args.stdin {
+        return Ok(vec![("<stdin>".to_string(), read_stdin())]);
+    }
+
+    let mut files = args.files.clone();
+    if let Some(dir) = &args.dir {
+        files.extend(list_files(dir)?.iter().map(|name| dir.join(name)));
+    }
+    Ok(files_to_inputs(files))
+}
+
+// Pair every file name with its contents
+fn files_to_inputs(files: Vec<PathBuf>) -> Vec<(String, Result<String>)> {
+    files
+        .into_iter()
+        .map(|file| {
+            let name = file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.display().to_string());
+            (name, read_ciphertext(&file))
+        })
+        .collect()
+}
+
+// Configure the cracker from the crack subcommand's options
+fn build_cracker(args: &CrackArgs) -> Result<Cracker> {
+    let mut cracker = Cracker::new();
+    if !args.strategies.is_empty() {
+        cracker.strategies = args.strategies.clone();
+    }
+    cracker.retry_top = args.retry;
+    cracker.fitness = args.fitness;
+    cracker.refine = !args.no_refine;
+    cracker.key_advance = args.key_advance;
+    cracker.autokey = args.autokey;
+    cracker.key_length = args.period.map(|period| period.get());
+    cracker.quagmire = args.quagmire.map(|kind| {
+        let mut config = QuagmireConfig {
+            kind,
+            seed: args.seed,
+            ..QuagmireConfig::default()
+        };
+        if let Some(restarts) = args.restarts {
+            config.restarts = restarts;
+        }
+        config
+    });
+    cracker.variant = match args.variant {
+        VariantChoice::Auto => None,
+        VariantChoice::Vigenere => Some(CipherVariant::Vigenere),
+        VariantChoice::Beaufort => Some(CipherVariant::Beaufort),
+        VariantChoice::VariantBeaufort => Some(CipherVariant::VariantBeaufort),
+        VariantChoice::Gronsfeld => Some(CipherVariant::Gronsfeld),
+        VariantChoice::Porta => Some(CipherVariant::Porta),
+    };
+    cracker.crib = args.crib.clone();
+    cracker.locks = args.key_pattern.clone().unwrap_or_default();
+    for &(position, letter) in &args.fix {
+        cracker.locks.lock(position, letter)?;
+    }
+    if let Some(wordlist) = &args.wordlist {
+        let mut dictionary = DictionaryConfig::new(parse_wordlist(&fs::read_to_string(wordlist)?));
+        dictionary.pairs = args.word_pairs;
+        cracker.dictionary = Some(dictionary);
+    }
+    cracker.solver = match args.solver {
+        SolverKind::ChiSquared => KeySolver::ChiSquared,
+        SolverKind::Annealing => {
+            let mut config = AnnealingConfig {
+                seed: args.seed,
+                ..AnnealingConfig::default()
+            };
+            if let Some(iterations) = args.iterations {
+                config.iterations = iterations;
+            }
+            if let Some(temperature) = args.initial_temperature {
+                config.initial_temperature = temperature;
+            }
+            if let Some(temperature) = args.final_temperature {
+                config.final_temperature = temperature;
+            }
+            if let Some(schedule) = args.schedule {
+                config.schedule = schedule;
+            }
+            KeySolver::Annealing(config)
+        }
+        SolverKind::Genetic => {
+            let mut config = GeneticConfig {
+                seed: args.seed,
+                ..GeneticConfig::default()
+            };
+            if let Some(population) = args.population {
+                config.population_size = population;
+            }
+            if let Some(generations) = args.generations {
+                config.generations = generations;
+            }
+            if let Some(rate) = args.crossover_rate {
+                config.crossover_rate = rate;
+            }
+            if let Some(rate) = args.mutation_rate {
+                config.mutation_rate = rate;
+            }
+            if let Some(elitism) = args.elitism {
+                config.elitism = elitism;
+            }
+            KeySolver::Genetic(config)
+        }
+        SolverKind::Beam => {
+            let mut config = BeamConfig::default();
+            if let Some(shifts) = args.beam_shifts {
+                config.shifts_per_column = shifts;
+            }
+            if let Some(width) = args.beam_width {
+                config.beam_width = width;
+            }
+            if let Some(keys) = args.beam_keys {
+                config.keys = keys;
+            }
+            KeySolver::Beam(config)
+        }
+    };
+    Ok(cracker)
+}
+
+// Crack the inputs of the crack subcommand, or those picked interactively or piped in
+// without one. Returns whether every input was cracked.
+fn crack(args: Option<&CrackArgs>, format: OutputFormat) -> bool {
+    let cracker = match args {
+        Some(args) => build_cracker(args),
+        None => Ok(Cracker::new()),
+    };
+    let cracker = match cracker {
+        Ok(cracker) => cracker,
+        Err(e) => {
+            error!("Error configuring the cracker: {}", e);
+            return false;
+        }
+    };
+    // Only fall back to the interactive picker when a person is at the terminal
+    let inputs = match args {
+        Some(args) => collect_inputs(args),
+        None if io::stdin().is_terminal() => {
+            select_files(Path::new("./input")).map(files_to_inputs)
+        }
+        None => Ok(vec![("<stdin>".to_string(), read_stdin())]),
+    };
+    let inputs = match inputs {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            error!("Error collecting inputs: {}", e);
+            return false;
         }
     };
 
     // Begin timing
     let start_time = Instant::now();
     let mut results = Vec::new();
+    let mut failures = 0;
 
-    // Process each file
-    for selected_file in selected_files {
-        let ciphertext_file = input_path.join(&selected_file);
-        let ciphertext = match read_ciphertext(&ciphertext_file) {
-            Ok(content) => content,
+    // Process each file, continuing past any that cannot be cracked
+    for (name, ciphertext) in inputs {
+        // Run decryption
+        match ciphertext.and_then(|ciphertext| cracker.crack(&ciphertext)) {
+            Ok(result) => results.push((name, result)),
             Err(e) => {
-                log_debug(format!(
-                    "Error reading file {}: {}",
-                    ciphertext_file.display(),
-                    e
-                ));
-                std::process::exit(1);
+                error!("Error cracking {}: {}", name, e);
+                failures += 1;
             }
-        };
-        // Run decryption
-        let result = run(ciphertext);
-        results.push((selected_file, result));
+        }
     }
 
     // Summarize results
-    log_info(format!("\nSummary:"));
-    for (file, (decrypted_text, elapsed, ic, key_length, key, confidence)) in results {
-        log_info(format!("File: {}", file));
-        log_timing(format!(
-            "Decryption took {} seconds and {} milliseconds",
-            elapsed.as_secs(),
-            elapsed.subsec_millis()
-        ));
-        log_info(format!("Index of Coincidence: {:.6}", ic));
-        log_info(format!("Estimated key length: {}", key_length));
-        log_info(format!("Decrypted key: {}", key));
-        log_info(format!(
-            "Decrypted text with confidence {:.2}%: {}",
-            100.0 - (confidence * 1000.0),
-            decrypted_text
-        ));
+    for (file, result) in &results {
+        info!(
+            "Decryption of {} took {} seconds and {} milliseconds",
+            file,
+            result.timings.total.as_secs(),
+            result.timings.total.subsec_millis()
+        );
+    }
+    if let Err(e) = write_results(io::stdout().lock(), format, &results) {
+        error!("Error writing results: {}", e);
+        return false;
     }
+
     let total_elapsed = start_time.elapsed();
     // Print the duration in a human-readable format
-    log_timing(format!(
+    info!(
         "All decryption(s) took {} seconds and {} milliseconds",
         total_elapsed.as_secs(),
         total_elapsed.subsec_millis()
-    ));
+    );
+
+    failures == 0
 }
 
-fn run(ciphertext: String) -> (String, Duration, f64, usize, String, f64) {
-    let mut summary = Vec::new();
+// Encrypt the inputs of the encrypt subcommand. Returns whether every input was encrypted.
+fn encrypt(args: &EncryptArgs, format: OutputFormat, quiet: bool) -> bool {
+    let inputs = if args.files.is_empty() {
+        vec![("<stdin>".to_string(), read_stdin())]
+    } else {
+        files_to_inputs(args.files.clone())
+    };
+    let mut rng = seeded_rng(args.seed);
+    let key_style = match args.variant {
+        CipherVariant::Gronsfeld => KeyStyle::Digits,
+        _ => args.key_style,
+    };
+    let mut encryptions = Vec::new();
+    let mut failures = 0;
 
-    // Time and run text analysis
-    let start_time = Instant::now();
-    let (ic, possible_key_lengths) = analyze_text(&ciphertext);
-    let analyze_text_duration = start_time.elapsed();
-    summary.push(("Analyze text", analyze_text_duration));
+    for (name, plaintext) in inputs {
+        let key = match (&args.key, args.random_key) {
+            (Some(key), _) => key.to_ascii_uppercase(),
+            (None, Some(length)) => generate_key(&mut rng, length.get(), key_style),
+            (None, None) => unreachable!("clap requires a key or a random key length"),
+        };
+        match plaintext.and_then(|plaintext| {
+            if args.autokey {
+                autokey_encrypt(&plaintext, &key, args.key_advance)
+            } else if let Some(kind) = args.quagmire {
+                // Quagmire II keys its ciphertext alphabet with the one keyword given
+                let keyword = args.alphabet_key.as_deref().unwrap_or_default();
+                let (plaintext_keyword, ciphertext_keyword) = match kind {
+                    QuagmireKind::Two => ("", keyword),
+                    _ => (
+                        keyword,
+                        args.cipher_alphabet_key.as_deref().unwrap_or_default(),
+                    ),
+                };
+                QuagmireKey::new(kind, plaintext_keyword, ciphertext_keyword, &key)?
+                    .encrypt(&plaintext, args.key_advance)
+            } else {
+                args.variant.encrypt(&plaintext, &key, args.key_advance)
+            }
+        }) {
+            Ok(ciphertext) => {
+                // Bare ciphertexts carry no key, so report generated ones separately
+                if format == OutputFormat::Text && args.random_key.is_some() && !quiet {
+                    eprintln!("Key for {}: {}", name, key);
+                }
+                encryptions.push(Encryption {
+                    file: name,
+                    key,
+                    ciphertext,
+                });
+            }
+            Err(e) => {
+                error!("Error encrypting {}: {}", name, e);
+                failures += 1;
+            }
+        }
+    }
 
-    // Time and run key length estimation
-    let start_time = Instant::now();
-    let key_length = estimate_key_length_using_multiple_strategies(
-        &[
-            KeyLengthEstimationStrategy::Autocorrelation,
-            KeyLengthEstimationStrategy::GCD,
-        ],
-        possible_key_lengths.clone(),
-        &ciphertext,
-        None,
-        5.0,
-    );
-    let estimate_key_length_duration = start_time.elapsed();
-    summary.push(("Estimate key length", estimate_key_length_duration));
+    if let Err(e) = write_encryptions(io::stdout().lock(), format, &encryptions) {
+        error!("Error writing results: {}", e);
+        return false;
+    }
+    failures == 0
+}
 
-    // Time and run decryption
-    let start_time = Instant::now();
-    let (key, decrypted_text, confidence) =
-        decrypt_vigenere(&ciphertext.to_uppercase(), key_length, None);
-    let decrypt_vigenere_duration = start_time.elapsed();
-    summary.push(("Decrypt Vigenere", decrypt_vigenere_duration));
-
-    // Calculate total duration
-    let total_duration =
-        analyze_text_duration + estimate_key_length_duration + decrypt_vigenere_duration;
-
-    // Print Summary
-    for (name, duration) in summary {
-        log_timing(format!(
-            "{} took {} seconds and {} milliseconds",
-            name,
-            duration.as_secs(),
-            duration.subsec_millis()
-        ));
-    }
-
-    log_timing(format!(
-        "Total decryption time: {} seconds and {} milliseconds",
-        total_duration.as_secs(),
-        total_duration.subsec_millis()
-    ));
-
-    (
-        decrypted_text,
-        total_duration,
-        ic,
-        key_length,
-        key,
-        confidence,
-    )
+// Print the keys asked for by the keygen subcommand
+fn keygen(args: &KeygenArgs, format: OutputFormat) -> bool {
+    let mut rng = seeded_rng(args.seed);
+    let keys: Vec<String> = (0..args.count)
+        .map(|_| generate_key(&mut rng, args.length.get(), args.style))
+        .collect();
+    if let Err(e) = write_keys(io::stdout().lock(), format, &keys) {
+        error!("Error writing results: {}", e);
+        return false;
+    }
+    true
+}
+
+// Main
+fn main() {
+    let cli = Cli::parse();
+    let checked = match &cli.command {
+        Some(Command::Crack(args)) => args.check(),
+        Some(Command::Encrypt(args)) => args.check(),
+        Some(Command::Keygen(_)) | None => Ok(()),
+    };
+    if let Err(e) = checked {
+        e.exit();
+    }
+    init_logging(cli.verbose, cli.quiet);
+
+    let succeeded = match &cli.command {
+        Some(Command::Crack(args)) => crack(Some(args.as_ref()), cli.format),
+        Some(Command::Encrypt(args)) => encrypt(args, cli.format, cli.quiet),
+        Some(Command::Keygen(args)) => keygen(args, cli.format),
+        None => crack(None, cli.format),
+    };
+
+    if !succeeded {
+        std::process::exit(1);
+    }
 }
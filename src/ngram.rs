@@ -0,0 +1,106 @@
+// src/ngram.rs
+use lazy_static::lazy_static;
+
+lazy_static! {
+    pub(crate) static ref ENGLISH_BIGRAMS: NgramModel =
+        NgramModel::from_counts(2, include_str!("../data/english_bigrams.txt"));
+    pub(crate) static ref ENGLISH_TRIGRAMS: NgramModel =
+        NgramModel::from_counts(3, include_str!("../data/english_trigrams.txt"));
+    pub(crate) static ref ENGLISH_QUADGRAMS: NgramModel =
+        NgramModel::from_counts(4, include_str!("../data/english_quadgrams.txt"));
+}
+
+// Letter n-gram language model holding the log10 probability of every n-gram.
+pub(crate) struct NgramModel {
+    n: usize,
+    // Indexed by the n-gram read as a base 26 number.
+    log_probs: Vec<f64>,
+    // Expected log probability per n-gram of text drawn from the model itself.
+    expected: f64,
+    // Expected log probability per n-gram of uniformly random letters.
+    random: f64,
+}
+
+impl NgramModel {
+    // Builds a model from lines of the form "NGRAM COUNT".
+    // Unseen n-grams get a floor of a hundredth of a single occurrence.
+    pub(crate) fn from_counts(n: usize, table: &str) -> Self {
+        let counts: Vec<(usize, f64)> = table
+            .lines()
+            .filter_map(|line| {
+                let (ngram, count) = line.split_once(' ')?;
+                let ngram = ngram.as_bytes();
+                if ngram.len() != n || !ngram.iter().all(u8::is_ascii_uppercase) {
+                    return None;
+                }
+                let index = ngram
+                    .iter()
+                    .fold(0, |index, &b| index * 26 + (b - b'A') as usize);
+                Some((index, count.trim().parse().ok()?))
+            })
+            .collect();
+        let total: f64 = counts.iter().map(|(_, count)| count).sum();
+
+        let floor = (0.01 / total).log10();
+        let mut log_probs = vec![floor; 26usize.pow(n as u32)];
+        let mut expected = 0.0;
+        for &(index, count) in &counts {
+            let log_prob = (count / total).log10();
+            log_probs[index] = log_prob;
+            expected += count / total * log_prob;
+        }
+        let random = log_probs.iter().sum::<f64>() / log_probs.len() as f64;
+
+        NgramModel {
+            n,
+            log_probs,
+            expected,
+            random,
+        }
+    }
+
+    // Log probability of an n-gram of uppercase letters.
+    pub(crate) fn log_probability(&self, ngram: &[u8]) -> f64 {
+        let index = ngram
+            .iter()
+            .fold(0, |index, &b| index * 26 + (b - b'A') as usize);
+        self.log_probs[index]
+    }
+
+    // Sums the log probability of every n-gram in the letters of the text, ignoring other characters.
+    pub(crate) fn score(&self, text: &str) -> f64 {
+        self.log_probabilities(text).sum()
+    }
+
+    // Average log probability per n-gram, comparable between texts of different lengths.
+    pub(crate) fn average_score(&self, text: &str) -> f64 {
+        let (count, sum) = self
+            .log_probabilities(text)
+            .fold((0, 0.0), |(count, sum), log_prob| {
+                (count + 1, sum + log_prob)
+            });
+        if count == 0 {
+            return self.random;
+        }
+        sum / count as f64
+    }
+
+    // Places the average score of a text between random letters (0.0) and typical English (1.0).
+    pub(crate) fn englishness(&self, text: &str) -> f64 {
+        ((self.average_score(text) - self.random) / (self.expected - self.random)).clamp(0.0, 1.0)
+    }
+
+    fn log_probabilities<'a>(&'a self, text: &'a str) -> impl Iterator<Item = f64> + 'a {
+        let modulus = 26usize.pow(self.n as u32 - 1);
+        text.bytes()
+            .filter(u8::is_ascii_alphabetic)
+            .map(|b| (b.to_ascii_uppercase() - b'A') as usize)
+            .scan((0, 0), move |(index, seen), letter| {
+                *index = (*index % modulus) * 26 + letter;
+                *seen += 1;
+                Some((*index, *seen))
+            })
+            .filter(move |&(_, seen)| seen >= self.n)
+            .map(move |(index, _)| self.log_probs[index])
+    }
+}
@@ -0,0 +1,187 @@
+// src/output.rs
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use vigenere_cracker::{CipherVariant, CrackResult, QuagmireKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+// Full JSON record for one input file.
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    result: &'a CrackResult,
+}
+
+// Flat CSV row for one input file.
+#[derive(Serialize)]
+struct CsvRecord<'a> {
+    file: &'a str,
+    key: &'a str,
+    key_length: usize,
+    variant: CipherVariant,
+    autokey: bool,
+    quagmire: Option<QuagmireKind>,
+    plaintext_alphabet: &'a str,
+    ciphertext_alphabet: &'a str,
+    ic: f64,
+    confidence: f64,
+    fitness: f64,
+    analysis_ms: f64,
+    key_length_estimation_ms: f64,
+    decryption_ms: f64,
+    total_ms: f64,
+    dictionary_key: &'a str,
+    crib_key: &'a str,
+    plaintext: &'a str,
+}
+
+impl<'a> CsvRecord<'a> {
+    fn new(file: &'a str, result: &'a CrackResult) -> Self {
+        let timings = &result.timings;
+        CsvRecord {
+            file,
+            key: &result.key,
+            key_length: result.key_length,
+            variant: result.variant,
+            autokey: result.autokey,
+            quagmire: result.quagmire.as_ref().map(|key| key.kind),
+            plaintext_alphabet: result
+                .quagmire
+                .as_ref()
+                .map_or("", |key| key.plaintext_keyword.as_str()),
+            ciphertext_alphabet: result
+                .quagmire
+                .as_ref()
+                .map_or("", |key| key.ciphertext_keyword.as_str()),
+            ic: result.ic,
+            confidence: result.confidence,
+            fitness: result.fitness,
+            analysis_ms: timings.analysis.as_secs_f64() * 1000.0,
+            key_length_estimation_ms: timings.key_length_estimation.as_secs_f64() * 1000.0,
+            decryption_ms: timings.decryption.as_secs_f64() * 1000.0,
+            total_ms: timings.total.as_secs_f64() * 1000.0,
+            dictionary_key: result
+                .dictionary_hits
+                .first()
+                .map_or("", |hit| hit.key.as_str()),
+            crib_key: result
+                .crib_hits
+                .first()
+                .map_or("", |hit| hit.decryption.key.as_str()),
+            plaintext: &result.plaintext,
+        }
+    }
+}
+
+// Writes one record per input file to the given writer in the chosen format.
+pub fn write_results(
+    writer: impl Write,
+    format: OutputFormat,
+    results: &[(String, CrackResult)],
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => write_text(writer, results),
+        OutputFormat::Json => write_json(writer, results),
+        OutputFormat::Csv => write_csv(writer, results),
+    }
+}
+
+// An encrypted input with the key it was encrypted under.
+#[derive(Serialize)]
+pub struct Encryption {
+    pub file: String,
+    pub key: String,
+    pub ciphertext: String,
+}
+
+// Writes encrypted inputs to the given writer, the bare ciphertexts in text format.
+pub fn write_encryptions(
+    mut writer: impl Write,
+    format: OutputFormat,
+    encryptions: &[Encryption],
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            for encryption in encryptions {
+                write!(writer, "{}", encryption.ciphertext)?;
+                if !encryption.ciphertext.ends_with('\n') {
+                    writeln!(writer)?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json_lines(writer, encryptions),
+        OutputFormat::Csv => write_csv_rows(writer, encryptions),
+    }
+}
+
+// Key column for generated keys in JSON and CSV.
+#[derive(Serialize)]
+struct KeyRecord<'a> {
+    key: &'a str,
+}
+
+// Writes generated keys to the given writer, one per line in text format.
+pub fn write_keys(mut writer: impl Write, format: OutputFormat, keys: &[String]) -> io::Result<()> {
+    let records: Vec<KeyRecord> = keys.iter().map(|key| KeyRecord { key }).collect();
+    match format {
+        OutputFormat::Text => {
+            for key in keys {
+                writeln!(writer, "{}", key)?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => write_json_lines(writer, &records),
+        OutputFormat::Csv => write_csv_rows(writer, &records),
+    }
+}
+
+fn write_json_lines<T: Serialize>(mut writer: impl Write, records: &[T]) -> io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn write_csv_rows<T: Serialize>(writer: impl Write, records: &[T]) -> io::Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for record in records {
+        csv_writer.serialize(record)?;
+    }
+    csv_writer.flush()
+}
+
+fn write_text(mut writer: impl Write, results: &[(String, CrackResult)]) -> io::Result<()> {
+    for (file, result) in results {
+        writeln!(writer, "File: {}", file)?;
+        writeln!(writer, "{}", result)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+// Emits JSON Lines so records can be streamed one per line.
+fn write_json(writer: impl Write, results: &[(String, CrackResult)]) -> io::Result<()> {
+    let records: Vec<JsonRecord> = results
+        .iter()
+        .map(|(file, result)| JsonRecord { file, result })
+        .collect();
+    write_json_lines(writer, &records)
+}
+
+fn write_csv(writer: impl Write, results: &[(String, CrackResult)]) -> io::Result<()> {
+    let records: Vec<CsvRecord> = results
+        .iter()
+        .map(|(file, result)| CsvRecord::new(file, result))
+        .collect();
+    write_csv_rows(writer, &records)
+}
@@ -0,0 +1,153 @@
+// src/porta.rs
+use std::cmp::Ordering;
+
+use log::debug;
+
+use crate::decryption::{letters, plaintext_fitness, validate_key, KeyAdvance};
+use crate::error::Result;
+
+// Number of Porta tableaux, each shared by a pair of key letters (AB, CD, ..., YZ).
+pub(crate) const TABLEAUX: u8 = 13;
+
+// Swaps a letter (0-25) between the halves A-M and N-Z under tableau `t`. Every tableau is
+// its own inverse, so this both encrypts and decrypts.
+fn porta_letter(p: u8, t: u8) -> u8 {
+    if p < 13 {
+        13 + (p + t) % 13
+    } else {
+        (p - t) % 13
+    }
+}
+
+// Tableau selected by a key letter.
+fn tableau(k: u8) -> u8 {
+    (k.to_ascii_uppercase() - b'A') / 2
+}
+
+// Applies the tableau of a key letter to a character, keeping its case.
+fn porta_char(c: char, k: u8) -> char {
+    let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+    (porta_letter(c as u8 - base, tableau(k)) + base) as char
+}
+
+// Decrypts Porta ciphertext, advancing the key on every character. Porta is reciprocal, so
+// this also encrypts.
+pub(crate) fn porta_decrypt(ciphertext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+
+    Ok(ciphertext
+        .chars()
+        .zip(key.bytes().cycle())
+        .map(|(c, k)| {
+            if c.is_ascii_alphabetic() {
+                porta_char(c, k)
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Decrypts Porta ciphertext advancing the key only on letters.
+pub(crate) fn porta_decrypt_letters(ciphertext: &str, key: &str) -> Result<String> {
+    validate_key(key)?;
+
+    let mut key_letters = key.bytes().cycle();
+    Ok(ciphertext
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                porta_char(c, key_letters.next().expect("key is not empty"))
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Decrypts, or encrypts, under the key following a key advancement policy.
+pub(crate) fn decrypt(ciphertext: &str, key: &str, advance: KeyAdvance) -> Result<String> {
+    match advance {
+        KeyAdvance::LettersOnly => porta_decrypt_letters(ciphertext, key),
+        KeyAdvance::AllCharacters => porta_decrypt(ciphertext, key),
+        KeyAdvance::Strip => porta_decrypt(&letters(ciphertext), key),
+    }
+}
+
+// Finds the most likely key for a key length by picking, for every column, the tableau whose
+// decryption best follows English letter frequencies. Keys use the first letter of each pair.
+pub(crate) fn guess_key(text: &str, key_length: usize) -> String {
+    (0..key_length)
+        .map(|i| {
+            let column: Vec<u8> = text
+                .bytes()
+                .skip(i)
+                .step_by(key_length)
+                .filter(u8::is_ascii_alphabetic)
+                .map(|c| c.to_ascii_uppercase() - b'A')
+                .collect();
+            let column_fitness = |t: u8| {
+                let plaintext: String = column
+                    .iter()
+                    .map(|&c| (b'A' + porta_letter(c, t)) as char)
+                    .collect();
+                plaintext_fitness(&plaintext)
+            };
+
+            let best = (0..TABLEAUX)
+                .max_by(|&a, &b| {
+                    column_fitness(a)
+                        .partial_cmp(&column_fitness(b))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap();
+            debug!("Column {}: Best tableau: {}", i, best);
+            (b'A' + 2 * best) as char
+        })
+        .collect()
+}
+
+// One key letter per tableau, the first of each pair.
+pub(crate) fn key_letters() -> Vec<u8> {
+    (0..TABLEAUX).map(|t| b'A' + 2 * t).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::tests::assert_round_trips;
+
+    #[test]
+    fn porta_encrypts_the_textbook_example() {
+        assert_eq!(
+            porta_decrypt("DEFENDTHEEASTWALLOFTHECASTLE", "FORTIFICATION").unwrap(),
+            "SYNNJSCVRNRLAHUTUKUCVRYRLANY"
+        );
+        assert_eq!(
+            porta_decrypt("SYNNJSCVRNRLAHUTUKUCVRYRLANY", "FORTIFICATION").unwrap(),
+            "DEFENDTHEEASTWALLOFTHECASTLE"
+        );
+    }
+
+    #[test]
+    fn porta_is_its_own_inverse() {
+        assert_round_trips(
+            "Porta",
+            |text, advance| decrypt(text, "LEMON", advance),
+            |text, advance| decrypt(text, "LEMON", advance),
+        );
+    }
+
+    #[test]
+    fn porta_swaps_the_halves_of_the_alphabet() {
+        let ciphertext = porta_decrypt("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "AZ").unwrap();
+        for (p, c) in "ABCDEFGHIJKLMNOPQRSTUVWXYZ".bytes().zip(ciphertext.bytes()) {
+            assert_ne!(p < b'N', c < b'N', "{} -> {}", p as char, c as char);
+        }
+        // Both letters of a pair select the same tableau
+        assert_eq!(
+            porta_decrypt("ATTACKATDAWN", "LEMON").unwrap(),
+            porta_decrypt("ATTACKATDAWN", "KFNPM").unwrap()
+        );
+    }
+}
@@ -0,0 +1,756 @@
+// src/quagmire.rs
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use log::debug;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::decryption::{validate_key, KeyAdvance, ENGLISH_FREQUENCIES};
+use crate::error::{CrackError, Result};
+use crate::fitness::Fitness;
+
+// A permutation of the 26 letters, as letter indices.
+type Alphabet = [u8; 26];
+
+const STRAIGHT: Alphabet = {
+    let mut alphabet = [0; 26];
+    let mut i = 0;
+    while i < 26 {
+        alphabet[i] = i as u8;
+        i += 1;
+    }
+    alphabet
+};
+
+// Which of the plaintext and ciphertext alphabets of a periodic cipher are keyed by a
+// keyword. Straight alphabets on both sides give the Vigenère cipher.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum QuagmireKind {
+    // Keyed plaintext alphabet, straight ciphertext alphabet.
+    #[serde(rename = "quagmire-1")]
+    One,
+    // Straight plaintext alphabet, keyed ciphertext alphabet.
+    #[serde(rename = "quagmire-2")]
+    Two,
+    // Plaintext and ciphertext alphabets keyed by the same keyword.
+    #[default]
+    #[serde(rename = "quagmire-3")]
+    Three,
+    // Plaintext and ciphertext alphabets keyed by different keywords.
+    #[serde(rename = "quagmire-4")]
+    Four,
+}
+
+impl FromStr for QuagmireKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        match s
+            .strip_prefix("quagmire")
+            .unwrap_or(&s)
+            .trim_start_matches([' ', '-'])
+        {
+            "1" | "i" => Ok(QuagmireKind::One),
+            "2" | "ii" => Ok(QuagmireKind::Two),
+            "3" | "iii" => Ok(QuagmireKind::Three),
+            "4" | "iv" => Ok(QuagmireKind::Four),
+            _ => Err(format!("unknown Quagmire type {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for QuagmireKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuagmireKind::One => write!(f, "Quagmire I"),
+            QuagmireKind::Two => write!(f, "Quagmire II"),
+            QuagmireKind::Three => write!(f, "Quagmire III"),
+            QuagmireKind::Four => write!(f, "Quagmire IV"),
+        }
+    }
+}
+
+impl QuagmireKind {
+    fn keyed_plaintext(self) -> bool {
+        self != QuagmireKind::Two
+    }
+
+    fn keyed_ciphertext(self) -> bool {
+        self != QuagmireKind::One
+    }
+}
+
+// Complete key of a Quagmire cipher. Each indicator letter is the ciphertext letter that the
+// first letter of the plaintext alphabet encrypts to in its column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuagmireKey {
+    pub kind: QuagmireKind,
+    // Keyword of the plaintext alphabet, empty when it is straight.
+    pub plaintext_keyword: String,
+    // Keyword of the ciphertext alphabet, empty when it is straight.
+    pub ciphertext_keyword: String,
+    pub indicator: String,
+}
+
+impl QuagmireKey {
+    // Builds a key, ignoring the keyword of a straight alphabet. Quagmire III keys both
+    // alphabets with the plaintext keyword.
+    pub fn new(
+        kind: QuagmireKind,
+        plaintext_keyword: &str,
+        ciphertext_keyword: &str,
+        indicator: &str,
+    ) -> Result<Self> {
+        validate_key(indicator)?;
+        let keyword = |keyword: &str, keyed: bool| -> Result<String> {
+            if !keyed {
+                return Ok(String::new());
+            }
+            validate_key(keyword)?;
+            Ok(keyword.to_ascii_uppercase())
+        };
+        let plaintext_keyword = keyword(plaintext_keyword, kind.keyed_plaintext())?;
+        let ciphertext_keyword = match kind {
+            QuagmireKind::Three => plaintext_keyword.clone(),
+            _ => keyword(ciphertext_keyword, kind.keyed_ciphertext())?,
+        };
+
+        Ok(QuagmireKey {
+            kind,
+            plaintext_keyword,
+            ciphertext_keyword,
+            indicator: indicator.to_ascii_uppercase(),
+        })
+    }
+
+    // Decrypts the ciphertext under this key following a key advancement policy.
+    pub fn decrypt(&self, ciphertext: &str, advance: KeyAdvance) -> Result<String> {
+        let table = Table::new(
+            keyed_alphabet(&self.plaintext_keyword),
+            keyed_alphabet(&self.ciphertext_keyword),
+        );
+        let shifts = self.shifts(&table.cipher);
+        Ok(transform(ciphertext, &shifts, advance, |c, shift| {
+            table.decrypt(c, shift)
+        }))
+    }
+
+    // Encrypts the plaintext under this key following a key advancement policy.
+    pub fn encrypt(&self, plaintext: &str, advance: KeyAdvance) -> Result<String> {
+        let table = Table::new(
+            keyed_alphabet(&self.plaintext_keyword),
+            keyed_alphabet(&self.ciphertext_keyword),
+        );
+        let shifts = self.shifts(&table.cipher);
+        Ok(transform(plaintext, &shifts, advance, |p, shift| {
+            table.encrypt(p, shift)
+        }))
+    }
+
+    // Offset of every indicator letter in the ciphertext alphabet.
+    fn shifts(&self, cipher: &Alphabet) -> Vec<u8> {
+        self.indicator
+            .bytes()
+            .map(|k| position(cipher, k - b'A'))
+            .collect()
+    }
+}
+
+impl fmt::Display for QuagmireKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = |keyword: &str| {
+            if keyword.is_empty() {
+                "straight".to_string()
+            } else {
+                keyword.to_string()
+            }
+        };
+        write!(
+            f,
+            "{} (plaintext alphabet {}, ciphertext alphabet {}, indicator {})",
+            self.kind,
+            keyword(&self.plaintext_keyword),
+            keyword(&self.ciphertext_keyword),
+            self.indicator
+        )
+    }
+}
+
+// Settings of the Quagmire solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuagmireConfig {
+    pub kind: QuagmireKind,
+    // Hill climbs started from random alphabets, keeping the best.
+    pub restarts: usize,
+    // Seeds the random number generator for reproducible runs, random when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for QuagmireConfig {
+    fn default() -> Self {
+        QuagmireConfig {
+            kind: QuagmireKind::default(),
+            restarts: 16,
+            seed: None,
+        }
+    }
+}
+
+// Alphabet keyed by a keyword: its letters without repeats, then the rest of A-Z in order.
+pub(crate) fn keyed_alphabet(keyword: &str) -> Alphabet {
+    let keyword: Vec<u8> = keyword
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase() - b'A')
+        .collect();
+    keyed_letters(&keyword)
+}
+
+// Alphabet keyed by a keyword of letter indices.
+fn keyed_letters(keyword: &[u8]) -> Alphabet {
+    let mut alphabet = [0; 26];
+    let mut used = [false; 26];
+    let letters = keyword.iter().copied().chain(0..26);
+    let mut len = 0;
+    for letter in letters {
+        if !used[letter as usize] {
+            used[letter as usize] = true;
+            alphabet[len] = letter;
+            len += 1;
+        }
+    }
+    alphabet
+}
+
+// Shortest keyword that keys the alphabet, empty for the straight alphabet. The rest of A-Z
+// follows the keyword in order, so the keyword ends where the alphabet's ascending tail starts.
+pub(crate) fn alphabet_keyword(alphabet: &Alphabet) -> String {
+    let mut start = alphabet.len() - 1;
+    while start > 0 && alphabet[start - 1] < alphabet[start] {
+        start -= 1;
+    }
+    alphabet[..start]
+        .iter()
+        .map(|&letter| (b'A' + letter) as char)
+        .collect()
+}
+
+// Index of a letter in an alphabet.
+fn position(alphabet: &Alphabet, letter: u8) -> u8 {
+    alphabet
+        .iter()
+        .position(|&l| l == letter)
+        .expect("alphabets hold every letter") as u8
+}
+
+// Plaintext and ciphertext alphabets with their inverses for quick lookups.
+#[derive(Clone)]
+struct Table {
+    plain: Alphabet,
+    cipher: Alphabet,
+    plain_index: Alphabet,
+    cipher_index: Alphabet,
+}
+
+impl Table {
+    fn new(plain: Alphabet, cipher: Alphabet) -> Self {
+        let mut table = Table {
+            plain,
+            cipher,
+            plain_index: [0; 26],
+            cipher_index: [0; 26],
+        };
+        table.index();
+        table
+    }
+
+    // Rebuilds the inverses after the alphabets change.
+    fn index(&mut self) {
+        for i in 0..26 {
+            self.plain_index[self.plain[i] as usize] = i as u8;
+            self.cipher_index[self.cipher[i] as usize] = i as u8;
+        }
+    }
+
+    fn decrypt(&self, c: u8, shift: u8) -> u8 {
+        self.plain[((self.cipher_index[c as usize] + 26 - shift) % 26) as usize]
+    }
+
+    fn encrypt(&self, p: u8, shift: u8) -> u8 {
+        self.cipher[((self.plain_index[p as usize] + shift) % 26) as usize]
+    }
+}
+
+// Maps every letter through `f` with the shift of its key position, keeping case, and
+// advances the key following the policy.
+fn transform(text: &str, shifts: &[u8], advance: KeyAdvance, f: impl Fn(u8, u8) -> u8) -> String {
+    let mut position = 0;
+    text.chars()
+        .filter_map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                let letter = f(c as u8 - base, shifts[position % shifts.len()]);
+                position += 1;
+                Some((letter + base) as char)
+            } else {
+                match advance {
+                    KeyAdvance::LettersOnly => Some(c),
+                    KeyAdvance::AllCharacters => {
+                        position += 1;
+                        Some(c)
+                    }
+                    KeyAdvance::Strip => None,
+                }
+            }
+        })
+        .collect()
+}
+
+// Units modulo 26, the factors an alphabet can be decimated by.
+const DECIMATIONS: [usize; 12] = [1, 3, 5, 7, 9, 11, 15, 17, 19, 21, 23, 25];
+
+// Alphabet taking every `k`th letter of another from offset `r`.
+fn decimated(alphabet: &Alphabet, k: usize, r: usize) -> Alphabet {
+    let mut result = [0; 26];
+    for (i, letter) in result.iter_mut().enumerate() {
+        *letter = alphabet[(k * i + r) % 26];
+    }
+    result
+}
+
+// Recovers the alphabets and indicator of a Quagmire cipher with a known period. The
+// ciphertext alphabet's keyword is searched first: under the right one, the columns are shifts of
+// one simple substitution, so aligning them gives a text with the IC of English whatever the
+// plaintext alphabet. The plaintext alphabet is then hill climbed as a simple substitution of
+// the aligned text on plaintext fitness, or follows from the ciphertext alphabet for
+// Quagmire II and III. Both alphabets are reported by their shortest keywords. Non-letters of
+// the analysis text take key positions but are otherwise ignored. Returns the key and its
+// plaintext fitness.
+pub(crate) fn crack_quagmire(
+    text: &str,
+    key_length: usize,
+    fitness: Fitness,
+    config: &QuagmireConfig,
+) -> Result<(QuagmireKey, f64)> {
+    if key_length == 0 {
+        return Err(CrackError::InvalidKey(String::new()));
+    }
+    let letters: Vec<(usize, u8)> = text
+        .bytes()
+        .enumerate()
+        .filter(|(_, c)| c.is_ascii_alphabetic())
+        .map(|(i, c)| (i % key_length, c.to_ascii_uppercase() - b'A'))
+        .collect();
+    if letters.is_empty() {
+        return Err(CrackError::EmptyCiphertext);
+    }
+    let mut counts = vec![[0u32; 26]; key_length];
+    for &(column, c) in &letters {
+        counts[column][c as usize] += 1;
+    }
+    let search = Search {
+        kind: config.kind,
+        letters,
+        counts,
+        fitness,
+    };
+
+    let seed = config
+        .seed
+        .unwrap_or_else(|| StdRng::from_entropy().gen::<u64>());
+    let restarts = config.restarts.max(1) as u64;
+    let best_of = |climb: &(dyn Fn(&mut StdRng) -> (Alphabet, f64) + Sync)| {
+        (0..restarts)
+            .into_par_iter()
+            .map(|restart| climb(&mut StdRng::seed_from_u64(seed.wrapping_add(restart))))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .expect("at least one restart")
+            .0
+    };
+
+    let cipher = if config.kind.keyed_ciphertext() {
+        best_of(&|rng| search.climb_cipher(rng))
+    } else {
+        STRAIGHT
+    };
+    let table = match config.kind {
+        QuagmireKind::One | QuagmireKind::Four => {
+            let (_, shifts) = search.align(&cipher);
+            let plain = best_of(&|rng| search.climb_plain(&cipher, &shifts, rng));
+            Table::new(plain, cipher)
+        }
+        // A decimated ciphertext alphabet aligns the columns as well but decrypts differently
+        // against a straight plaintext alphabet, so each decimation is tried
+        QuagmireKind::Two => DECIMATIONS
+            .iter()
+            .map(|&k| Table::new(STRAIGHT, decimated(&cipher, k, 0)))
+            .max_by(|a, b| {
+                search
+                    .score(a)
+                    .partial_cmp(&search.score(b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .expect("decimations are not empty"),
+        QuagmireKind::Three => Table::new(cipher, cipher),
+    };
+
+    let table = search.normalized(&table);
+    let score = search.score(&table);
+    let key = search.key(&table);
+    debug!("Quagmire period {}: {} ({:.4})", key_length, key, score);
+    Ok((key, score))
+}
+
+// Ciphertext letters with their key columns, and the letter counts of every column.
+struct Search {
+    kind: QuagmireKind,
+    letters: Vec<(usize, u8)>,
+    counts: Vec<[u32; 26]>,
+    fitness: Fitness,
+}
+
+impl Search {
+    // Searches the ciphertext alphabet's keyword from a random start for the highest IC of
+    // the aligned columns.
+    fn climb_cipher(&self, rng: &mut StdRng) -> (Alphabet, f64) {
+        climb_keyword(rng, |cipher| self.align(cipher).0)
+    }
+
+    // Hill climbs letter swaps in the plaintext alphabet from a random start while the fitness
+    // of the aligned text decrypted as a simple substitution improves.
+    fn climb_plain(&self, cipher: &Alphabet, shifts: &[u8], rng: &mut StdRng) -> (Alphabet, f64) {
+        let mut cipher_index = [0; 26];
+        for (i, &letter) in cipher.iter().enumerate() {
+            cipher_index[letter as usize] = i as u8;
+        }
+        let aligned: Vec<u8> = self
+            .letters
+            .iter()
+            .map(|&(column, c)| (cipher_index[c as usize] + 26 - shifts[column]) % 26)
+            .collect();
+
+        let mut plain = STRAIGHT;
+        plain.shuffle(rng);
+        climb(plain, |plain| {
+            let plaintext: String = aligned
+                .iter()
+                .map(|&y| (b'A' + plain[y as usize]) as char)
+                .collect();
+            self.fitness.score(&plaintext)
+        })
+    }
+
+    // Aligns every column with the ones before it, reading ciphertext letters as their
+    // positions in the ciphertext alphabet. Returns the IC of the aligned text and the shift
+    // of every column.
+    fn align(&self, cipher: &Alphabet) -> (f64, Vec<u8>) {
+        let positions: Vec<[u32; 26]> = self
+            .counts
+            .iter()
+            .map(|counts| {
+                let mut positions = [0; 26];
+                for (x, &letter) in cipher.iter().enumerate() {
+                    positions[x] = counts[letter as usize];
+                }
+                positions
+            })
+            .collect();
+
+        let mut merged = positions[0];
+        let mut shifts = vec![0; positions.len()];
+        for (column, positions) in positions.iter().enumerate().skip(1) {
+            let overlap = |shift: usize| -> u64 {
+                (0..26)
+                    .map(|y| merged[y] as u64 * positions[(y + shift) % 26] as u64)
+                    .sum()
+            };
+            let shift = (0..26).max_by_key(|&shift| overlap(shift)).unwrap();
+            for (y, count) in merged.iter_mut().enumerate() {
+                *count += positions[(y + shift) % 26];
+            }
+            shifts[column] = shift as u8;
+        }
+
+        let total: u64 = merged.iter().map(|&count| count as u64).sum();
+        let coincidences: u64 = merged
+            .iter()
+            .map(|&count| count as u64 * (count as u64).saturating_sub(1))
+            .sum();
+        let ic = coincidences as f64 / (total * total.saturating_sub(1)).max(1) as f64;
+        (ic, shifts)
+    }
+
+    // Lowest chi-squared shift of every column under the alphabets.
+    fn shifts(&self, table: &Table) -> Vec<u8> {
+        self.counts
+            .iter()
+            .map(|counts| {
+                let total: u32 = counts.iter().sum();
+                (0..26)
+                    .min_by(|&a, &b| {
+                        chi_squared(table, counts, total, a)
+                            .partial_cmp(&chi_squared(table, counts, total, b))
+                            .unwrap_or(Ordering::Equal)
+                    })
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    // Fitness of the plaintext under the alphabets and their best shifts.
+    fn score(&self, table: &Table) -> f64 {
+        let shifts = self.shifts(table);
+        let plaintext: String = self
+            .letters
+            .iter()
+            .map(|&(column, c)| (b'A' + table.decrypt(c, shifts[column])) as char)
+            .collect();
+        self.fitness.score_decryption(&plaintext, shifts.len())
+    }
+
+    // Equivalent alphabets with the shortest keywords. Rotating a keyed alphabet only changes
+    // the shifts, and so does decimating the ciphertext alphabet along with the plaintext
+    // alphabet when that is keyed too.
+    fn normalized(&self, table: &Table) -> Table {
+        let shortest = |alphabets: &mut dyn Iterator<Item = Alphabet>| {
+            alphabets
+                .min_by_key(|alphabet| alphabet_keyword(alphabet).len())
+                .expect("at least one alphabet")
+        };
+        let rotations = |alphabet: Alphabet| (0..26).map(move |r| decimated(&alphabet, 1, r));
+
+        match self.kind {
+            QuagmireKind::One => Table::new(shortest(&mut rotations(table.plain)), table.cipher),
+            QuagmireKind::Two => Table::new(table.plain, shortest(&mut rotations(table.cipher))),
+            QuagmireKind::Three => {
+                let alphabet = shortest(
+                    &mut DECIMATIONS
+                        .iter()
+                        .flat_map(|&k| (0..26).map(move |r| decimated(&table.cipher, k, r))),
+                );
+                Table::new(alphabet, alphabet)
+            }
+            QuagmireKind::Four => DECIMATIONS
+                .iter()
+                .map(|&k| {
+                    Table::new(
+                        shortest(&mut rotations(decimated(&table.plain, k, 0))),
+                        shortest(&mut rotations(decimated(&table.cipher, k, 0))),
+                    )
+                })
+                .min_by_key(|table| {
+                    alphabet_keyword(&table.plain).len() + alphabet_keyword(&table.cipher).len()
+                })
+                .expect("decimations are not empty"),
+        }
+    }
+
+    // Key of the alphabets, with each indicator letter the ciphertext letter that the
+    // plaintext alphabet's first letter encrypts to under the column's best shift.
+    fn key(&self, table: &Table) -> QuagmireKey {
+        let indicator: String = self
+            .shifts(table)
+            .iter()
+            .map(|&shift| (b'A' + table.cipher[shift as usize]) as char)
+            .collect();
+        QuagmireKey {
+            kind: self.kind,
+            plaintext_keyword: alphabet_keyword(&table.plain),
+            ciphertext_keyword: alphabet_keyword(&table.cipher),
+            indicator,
+        }
+    }
+}
+
+// Longest keyword the keyword search grows to.
+const MAX_KEYWORD_LENGTH: usize = 16;
+
+// Steps of the annealing that precedes the keyword hill climb.
+const ANNEALING_STEPS: usize = 30000;
+
+// Starting temperature of the annealing, on the scale of IC differences, which cools
+// geometrically to a tenth of it.
+const ANNEALING_TEMPERATURE: f64 = 0.001;
+
+// Anneals a keyword from a random start, replacing, inserting, deleting and swapping letters,
+// then hill climbs it while the score of its keyed alphabet improves. A wrong keyword letter
+// shifts the rest of the alphabet, which leaves a plain hill climb stuck far from the answer.
+// Returns the alphabet and its score.
+fn climb_keyword(rng: &mut StdRng, score: impl Fn(&Alphabet) -> f64) -> (Alphabet, f64) {
+    let mut keyword: Vec<u8> = (0..rng.gen_range(3..=8))
+        .map(|_| rng.gen_range(0..26))
+        .collect();
+    let mut current = score(&keyed_letters(&keyword));
+    let mut best = current;
+    let mut best_keyword = keyword.clone();
+    for step in 0..ANNEALING_STEPS {
+        let temperature = ANNEALING_TEMPERATURE * 0.1f64.powf(step as f64 / ANNEALING_STEPS as f64);
+        let mut candidate = keyword.clone();
+        match rng.gen_range(0..4) {
+            0 => {
+                let at = rng.gen_range(0..candidate.len());
+                candidate[at] = rng.gen_range(0..26);
+            }
+            1 if candidate.len() < MAX_KEYWORD_LENGTH => {
+                let at = rng.gen_range(0..=candidate.len());
+                candidate.insert(at, rng.gen_range(0..26));
+            }
+            2 if candidate.len() > 1 => {
+                let at = rng.gen_range(0..candidate.len());
+                candidate.remove(at);
+            }
+            3 if candidate.len() > 1 => {
+                let a = rng.gen_range(0..candidate.len());
+                let b = rng.gen_range(0..candidate.len());
+                candidate.swap(a, b);
+            }
+            _ => continue,
+        }
+        let value = score(&keyed_letters(&candidate));
+        if value >= current || rng.gen::<f64>() < ((value - current) / temperature).exp() {
+            keyword = candidate;
+            current = value;
+            if value > best {
+                best = value;
+                best_keyword = keyword.clone();
+            }
+        }
+    }
+    keyword = best_keyword;
+
+    loop {
+        let mut neighbours: Vec<Vec<u8>> = Vec::new();
+        for i in 0..keyword.len() {
+            for letter in 0..26 {
+                if letter != keyword[i] {
+                    let mut neighbour = keyword.clone();
+                    neighbour[i] = letter;
+                    neighbours.push(neighbour);
+                }
+            }
+            if keyword.len() > 1 {
+                let mut neighbour = keyword.clone();
+                neighbour.remove(i);
+                neighbours.push(neighbour);
+            }
+        }
+        if keyword.len() < MAX_KEYWORD_LENGTH {
+            for i in 0..=keyword.len() {
+                for letter in 0..26 {
+                    let mut neighbour = keyword.clone();
+                    neighbour.insert(i, letter);
+                    neighbours.push(neighbour);
+                }
+            }
+        }
+
+        let Some((neighbour, candidate)) = neighbours
+            .into_iter()
+            .map(|neighbour| {
+                let candidate = score(&keyed_letters(&neighbour));
+                (neighbour, candidate)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        else {
+            break;
+        };
+        if candidate <= best {
+            break;
+        }
+        keyword = neighbour;
+        best = candidate;
+    }
+    (keyed_letters(&keyword), best)
+}
+
+// Swaps pairs of letters in an alphabet, keeping every swap that raises the score, until no
+// swap helps. Returns the alphabet and its score.
+fn climb(mut alphabet: Alphabet, score: impl Fn(&Alphabet) -> f64) -> (Alphabet, f64) {
+    let mut best = score(&alphabet);
+    loop {
+        let mut improved = false;
+        for i in 0..26 {
+            for j in i + 1..26 {
+                alphabet.swap(i, j);
+                let candidate = score(&alphabet);
+                if candidate > best {
+                    best = candidate;
+                    improved = true;
+                } else {
+                    alphabet.swap(i, j);
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    (alphabet, best)
+}
+
+// Chi-squared of a column's plaintext letter counts under a shift against English.
+fn chi_squared(table: &Table, counts: &[u32; 26], total: u32, shift: u8) -> f64 {
+    let mut plain_counts = [0u32; 26];
+    for (c, &count) in counts.iter().enumerate() {
+        plain_counts[table.decrypt(c as u8, shift) as usize] += count;
+    }
+    let total = total.max(1) as f64;
+    plain_counts
+        .iter()
+        .zip(ENGLISH_FREQUENCIES.iter())
+        .map(|(&observed, &(_, frequency))| {
+            let expected = frequency * total;
+            (observed as f64 - expected).powi(2) / expected
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decryption::tests::assert_round_trips;
+
+    const KINDS: [QuagmireKind; 4] = [
+        QuagmireKind::One,
+        QuagmireKind::Two,
+        QuagmireKind::Three,
+        QuagmireKind::Four,
+    ];
+
+    #[test]
+    fn decrypt_inverts_encrypt_for_every_kind_and_policy() {
+        for kind in KINDS {
+            let key = QuagmireKey::new(kind, "SPRINGFEVER", "CHAMPIONSHIP", "FLOWER").unwrap();
+            assert_round_trips(
+                &kind.to_string(),
+                |text, advance| key.encrypt(text, advance),
+                |text, advance| key.decrypt(text, advance),
+            );
+        }
+    }
+
+    #[test]
+    fn straight_alphabets_give_vigenere() {
+        let key = QuagmireKey::new(QuagmireKind::One, "A", "", "LEMON").unwrap();
+        assert_eq!(
+            key.encrypt("ATTACKATDAWN", KeyAdvance::LettersOnly)
+                .unwrap(),
+            "LXFOPVEFRNHR"
+        );
+    }
+
+    #[test]
+    fn alphabet_keywords_round_trip() {
+        let alphabet = keyed_alphabet("SPRINGFEVER");
+        assert_eq!(alphabet_keyword(&alphabet), "SPRINGFEV");
+        assert_eq!(keyed_alphabet(&alphabet_keyword(&alphabet)), alphabet);
+        assert_eq!(alphabet_keyword(&STRAIGHT), "");
+        assert_eq!(keyed_alphabet(""), STRAIGHT);
+    }
+}
@@ -0,0 +1,49 @@
+// src/solver.rs
+use crate::annealing::{anneal_key, AnnealingConfig};
+use crate::beam_search::{beam_search_keys, BeamConfig};
+use crate::decryption::guess_key;
+use crate::error::Result;
+use crate::fitness::Fitness;
+use crate::genetic_decryption::{genetic_guess_key, GeneticConfig};
+use crate::key_locks::KeyLocks;
+
+// Method used to find the key once its length is known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum KeySolver {
+    // Picks each column's shift independently by minimum chi-squared.
+    #[default]
+    ChiSquared,
+    // Simulated annealing over whole keys, starting from the chi-squared guess.
+    Annealing(AnnealingConfig),
+    // Genetic algorithm over whole keys, its population seeded with the chi-squared guess.
+    Genetic(GeneticConfig),
+    // Beam search over combinations of each column's lowest chi-squared shifts.
+    Beam(BeamConfig),
+}
+
+impl KeySolver {
+    // Finds the most likely keys of the given length from best to worst, a single key
+    // unless the solver produces several.
+    pub fn solve_ranked(
+        &self,
+        ciphertext: &str,
+        key_length: usize,
+        fitness: Fitness,
+        locks: &KeyLocks,
+    ) -> Result<Vec<String>> {
+        if let KeySolver::Beam(config) = self {
+            let keys = beam_search_keys(ciphertext, key_length, fitness, locks, config)?;
+            return Ok(keys.into_iter().map(|(key, _)| key).collect());
+        }
+
+        let key = locks.applied(&guess_key(ciphertext, key_length));
+        let key = match self {
+            KeySolver::Annealing(config) => anneal_key(ciphertext, &key, fitness, locks, config)?,
+            KeySolver::Genetic(config) => {
+                genetic_guess_key(ciphertext, &key, fitness, locks, config)?
+            }
+            KeySolver::ChiSquared | KeySolver::Beam(_) => key,
+        };
+        Ok(vec![key])
+    }
+}